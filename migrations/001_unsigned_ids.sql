@@ -0,0 +1,21 @@
+-- Upgrades a database created from the original schema.sql: ids become INT UNSIGNED to match the
+-- u32 ids used by the code.
+--
+-- The foreign keys have to be dropped while the columns they join change type. The names below
+-- are the ones MySQL and MariaDB generate for the unnamed keys in schema.sql; check them with
+-- SHOW CREATE TABLE if the tables were created some other way.
+ALTER TABLE project_tags DROP FOREIGN KEY project_tags_ibfk_1;
+ALTER TABLE project_links DROP FOREIGN KEY project_links_ibfk_1;
+
+ALTER TABLE projects MODIFY id INT UNSIGNED NOT NULL AUTO_INCREMENT;
+ALTER TABLE project_tags
+    MODIFY id INT UNSIGNED NOT NULL AUTO_INCREMENT,
+    MODIFY project_id INT UNSIGNED NOT NULL;
+ALTER TABLE project_links
+    MODIFY id INT UNSIGNED NOT NULL AUTO_INCREMENT,
+    MODIFY project_id INT UNSIGNED NOT NULL;
+
+ALTER TABLE project_tags
+    ADD FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE;
+ALTER TABLE project_links
+    ADD FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE;
@@ -0,0 +1,10 @@
+-- Full snapshot of every write (kept after the project itself is deleted).
+CREATE TABLE IF NOT EXISTS project_revisions (
+    id INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    project_id INT UNSIGNED NOT NULL,
+    revision INT UNSIGNED NOT NULL,
+    author VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    snapshot LONGTEXT NOT NULL, -- JSON
+    UNIQUE (project_id, revision)
+);
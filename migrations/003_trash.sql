@@ -0,0 +1,3 @@
+-- Projects are moved to the trash instead of being deleted right away.
+ALTER TABLE projects
+    ADD deleted_at TIMESTAMP NULL DEFAULT NULL; -- set while the project is in the trash
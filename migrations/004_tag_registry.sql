@@ -0,0 +1,14 @@
+-- Canonical tags with display metadata, and the other spellings that resolve to them.
+CREATE TABLE IF NOT EXISTS tags (
+    id INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(100) NOT NULL UNIQUE,
+    color VARCHAR(32), -- optional CSS color
+    icon TEXT, -- optional icon name or URL
+    description TEXT
+);
+
+CREATE TABLE IF NOT EXISTS tag_aliases (
+    alias VARCHAR(100) PRIMARY KEY,
+    tag_id INT UNSIGNED NOT NULL,
+    FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+);
@@ -0,0 +1,4 @@
+-- Tags can have a parent tag.
+ALTER TABLE tags
+    ADD parent_id INT UNSIGNED, -- optional parent tag
+    ADD FOREIGN KEY (parent_id) REFERENCES tags(id) ON DELETE SET NULL;
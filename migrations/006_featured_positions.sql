@@ -0,0 +1,4 @@
+-- Projects can be featured and ordered by hand.
+ALTER TABLE projects
+    ADD featured BOOLEAN NOT NULL DEFAULT FALSE AFTER cover, -- listed before the others
+    ADD position INT UNSIGNED NOT NULL DEFAULT 0 AFTER featured; -- manual ordering, lowest first
@@ -0,0 +1,5 @@
+-- Links get a role, an explicit order and a primary flag.
+ALTER TABLE project_links
+    ADD role VARCHAR(16), -- source, demo, docs, download, article or video
+    ADD position INT UNSIGNED, -- explicit order, lowest first
+    ADD is_primary BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,10 @@
+-- Outcome of the last check of each link and cover URL.
+CREATE TABLE IF NOT EXISTS link_checks (
+    url TEXT NOT NULL,
+    url_hash CHAR(64) AS (SHA2(url, 256)) STORED UNIQUE,
+    status SMALLINT UNSIGNED, -- last HTTP status, NULL if no response arrived
+    final_url TEXT, -- where redirects ended up
+    permanent_redirect BOOLEAN NOT NULL DEFAULT FALSE, -- every redirect was a 301 or 308
+    error TEXT,
+    checked_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -1,25 +1,73 @@
+-- Creates a fresh database. Existing databases are upgraded with the scripts in migrations/,
+-- in order.
+
 -- Main project table
 CREATE TABLE projects (
-    id INT AUTO_INCREMENT PRIMARY KEY,
+    id INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
     title VARCHAR(255) NOT NULL,
     description TEXT NOT NULL,
-    cover TEXT -- optional URL
+    cover TEXT, -- optional URL
+    featured BOOLEAN NOT NULL DEFAULT FALSE, -- listed before the others
+    position INT UNSIGNED NOT NULL DEFAULT 0, -- manual ordering, lowest first
+    deleted_at TIMESTAMP NULL DEFAULT NULL -- set while the project is in the trash
 );
 
 -- Tags (one-to-many relationship with projects)
 CREATE TABLE project_tags (
-    id INT AUTO_INCREMENT PRIMARY KEY,
-    project_id INT NOT NULL,
+    id INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    project_id INT UNSIGNED NOT NULL,
     tag VARCHAR(100) NOT NULL,
     FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
 );
 
 -- Links (one-to-many relationship with projects)
 CREATE TABLE project_links (
-    id INT AUTO_INCREMENT PRIMARY KEY,
-    project_id INT NOT NULL,
+    id INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    project_id INT UNSIGNED NOT NULL,
     name VARCHAR(100) NOT NULL,
     link TEXT NOT NULL,
+    role VARCHAR(16), -- source, demo, docs, download, article or video
+    position INT UNSIGNED, -- explicit order, lowest first
+    is_primary BOOLEAN NOT NULL DEFAULT FALSE,
     FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
 );
 
+-- Full snapshot of every write (kept after the project itself is deleted)
+CREATE TABLE project_revisions (
+    id INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    project_id INT UNSIGNED NOT NULL,
+    revision INT UNSIGNED NOT NULL,
+    author VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    snapshot LONGTEXT NOT NULL, -- JSON
+    UNIQUE (project_id, revision)
+);
+
+-- Canonical tags and how frontends should display them
+CREATE TABLE tags (
+    id INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(100) NOT NULL UNIQUE,
+    color VARCHAR(32), -- optional CSS color
+    icon TEXT, -- optional icon name or URL
+    description TEXT,
+    parent_id INT UNSIGNED, -- optional parent tag
+    FOREIGN KEY (parent_id) REFERENCES tags(id) ON DELETE SET NULL
+);
+
+-- Other spellings that resolve to a canonical tag
+CREATE TABLE tag_aliases (
+    alias VARCHAR(100) PRIMARY KEY,
+    tag_id INT UNSIGNED NOT NULL,
+    FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+);
+
+-- Outcome of the last check of each link and cover URL
+CREATE TABLE link_checks (
+    url TEXT NOT NULL,
+    url_hash CHAR(64) AS (SHA2(url, 256)) STORED UNIQUE,
+    status SMALLINT UNSIGNED, -- last HTTP status, NULL if no response arrived
+    final_url TEXT, -- where redirects ended up
+    permanent_redirect BOOLEAN NOT NULL DEFAULT FALSE, -- every redirect was a 301 or 308
+    error TEXT,
+    checked_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,602 @@
+//! JSON API mounted under `/api`. Routes that change data require [`Admin`].
+use rocket::{
+    Build, Request, Rocket, Route, delete,
+    fairing::{self, Fairing, Info, Kind},
+    figment::Figment,
+    get,
+    http::{ContentType, Status},
+    patch, post, put,
+    request::{FromRequest, Outcome},
+    response::status::Created,
+    routes,
+    serde::json::{Json, Value},
+};
+use rocket_db_pools::{Connection, sqlx::MySqlConnection};
+use serde::Deserialize;
+
+use crate::{
+    ApiError, Project, Projects,
+    builders::ProjectBuilder,
+    db::BloggerDatabase,
+    etag::{Conditional, IfMatch, Tagged},
+    filter::{Page, ProjectFilter},
+    graph::TagGraph,
+    health::HealthReport,
+    links::Button,
+    order::Reorder,
+    patch::Patch,
+    query::Query,
+    related::{self, Related, RelatedProject},
+    revisions::{Revision, RevisionSummary},
+    rewrite::{LinkRewrite, RewriteReport},
+    search::{self, SearchHit, SearchIndex},
+    tags::{Tag, TagNode, TagOperation, TagRegistry, TagReport},
+    trash::Trash,
+};
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        list_projects,
+        get_project,
+        project_buttons,
+        related_projects,
+        create_project,
+        reorder_projects,
+        update_project,
+        merge_patch_project,
+        json_patch_project,
+        delete_project,
+        list_revisions,
+        get_revision,
+        restore_revision,
+        list_trash,
+        restore_from_trash,
+        purge_from_trash,
+        list_tags,
+        get_tag,
+        save_tag,
+        tag_operation,
+        tag_graph,
+        tag_graph_dot,
+        link_health,
+        rewrite_links,
+        search_projects,
+    ]
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    /// Token that routes changing data expect as `Authorization: Bearer <token>`. Without one,
+    /// every write is refused.
+    pub admin_token: Option<String>,
+}
+impl ApiConfig {
+    /// Reads `[api]`, falling back to the defaults if the section is missing.
+    pub fn from_figment(figment: &Figment) -> Result<Self, Box<rocket::figment::Error>> {
+        if figment.contains("api") {
+            figment.extract_inner("api").map_err(Box::new)
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+/// Loads the `[api]` section from `Rocket.toml` for the [`Admin`] guard.
+pub struct AdminToken;
+
+#[rocket::async_trait]
+impl Fairing for AdminToken {
+    fn info(&self) -> Info {
+        Info {
+            name: "Admin Token",
+            kind: Kind::Ignite,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        match ApiConfig::from_figment(rocket.figment()) {
+            Ok(config) => {
+                if config.admin_token.is_none() {
+                    log::warn!("no [api] admin_token set, the API is read-only");
+                }
+                Ok(rocket.manage(config))
+            }
+            Err(e) => {
+                log::error!("invalid [api] config: {e}");
+                Err(rocket)
+            }
+        }
+    }
+}
+
+/// A request carrying the admin token from `[api]`. Fails with 401 otherwise.
+pub struct Admin;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let expected = request
+            .rocket()
+            .state::<ApiConfig>()
+            .and_then(|config| config.admin_token.as_deref());
+        let given = request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|header| header.strip_prefix("Bearer "));
+        match (expected, given) {
+            (Some(expected), Some(given)) if same_token(expected, given) => Outcome::Success(Admin),
+            _ => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
+
+/// Compares without returning early, so the time taken doesn't reveal how much of a guess matched.
+fn same_token(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Who is making a change, taken from the `X-Author` header. Only admins can make changes, so
+/// this requires [`Admin`] too.
+pub struct Author(pub String);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Author {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        request.guard::<Admin>().await.map(|Admin| {
+            let author = request
+                .headers()
+                .get_one("X-Author")
+                .filter(|author| !author.is_empty())
+                .unwrap_or("admin");
+            Author(author.to_string())
+        })
+    }
+}
+
+/// Refreshes everything derived from the stored projects after a successful write.
+async fn after_write(db: &mut MySqlConnection) {
+    search::reindex(db).await;
+    related::recompute(db).await;
+}
+
+fn require_if_match(if_match: &IfMatch) -> Result<(), ApiError> {
+    match if_match.0 {
+        Some(_) => Ok(()),
+        None => Err(ApiError::PreconditionRequired),
+    }
+}
+
+fn conditional(project: Project, result: Conditional) -> Result<Tagged, ApiError> {
+    match result {
+        Conditional::Updated => Ok(project.into()),
+        Conditional::NotFound => Err(ApiError::NotFound("Project")),
+        Conditional::Changed(current) => Err(ApiError::PreconditionFailed(current)),
+    }
+}
+
+/// Lists live projects a page at a time. See [`ProjectFilter`] for the query parameters.
+#[get("/projects?<filter..>")]
+async fn list_projects(
+    filter: ProjectFilter,
+    mut db: Connection<BloggerDatabase>,
+) -> Result<Json<Page>, ApiError> {
+    let expression = filter
+        .query
+        .as_deref()
+        .filter(|query| !query.trim().is_empty())
+        .map(Query::parse)
+        .transpose()?;
+    Projects::find(&filter, expression.as_ref(), &mut db)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::Cursor(filter.after.unwrap_or_default()))
+}
+
+#[get("/projects/<id>")]
+async fn get_project(id: u32, mut db: Connection<BloggerDatabase>) -> Result<Tagged, ApiError> {
+    Project::get(id, &mut db)
+        .await?
+        .map(Tagged::from)
+        .ok_or(ApiError::NotFound("Project"))
+}
+
+/// The links of project `id` as buttons, labelled by role with the primary link first.
+#[get("/projects/<id>/buttons")]
+async fn project_buttons(
+    id: u32,
+    mut db: Connection<BloggerDatabase>,
+) -> Result<Json<Vec<Button>>, ApiError> {
+    Project::get(id, &mut db)
+        .await?
+        .map(|project| Json(project.buttons()))
+        .ok_or(ApiError::NotFound("Project"))
+}
+
+/// Other projects most like project `id`, best first. `limit` defaults to
+/// [`related::DEFAULT_LIMIT`] and is capped at [`related::MAX_LIMIT`].
+#[get("/projects/<id>/related?<limit>")]
+fn related_projects(id: u32, limit: Option<usize>) -> Result<Json<Vec<RelatedProject>>, ApiError> {
+    let limit = limit
+        .unwrap_or(related::DEFAULT_LIMIT)
+        .clamp(1, related::MAX_LIMIT);
+    Related::global()
+        .related(id, limit)
+        .map(Json)
+        .ok_or(ApiError::NotFound("Project"))
+}
+
+#[post("/projects", data = "<project>")]
+async fn create_project(
+    project: Json<ProjectBuilder>,
+    author: Author,
+    mut db: Connection<BloggerDatabase>,
+) -> Result<Created<Tagged>, ApiError> {
+    let mut project = project.bulid()?;
+    project.id = None;
+    let id = project.insert(&author.0, &mut db).await?;
+    after_write(&mut db).await;
+    project.id = Some(id);
+    Ok(Created::new(format!("/api/projects/{id}")).body(project.into()))
+}
+
+/// Moves one project before or after another, or sets the order of many at once. Responds with
+/// the ids of the live projects in their new order.
+#[post("/projects/order", data = "<reorder>")]
+async fn reorder_projects(
+    reorder: Json<Reorder>,
+    _admin: Admin,
+    mut db: Connection<BloggerDatabase>,
+) -> Result<Json<Vec<u32>>, ApiError> {
+    let order = reorder.apply(&mut db).await?;
+    after_write(&mut db).await;
+    Ok(Json(order))
+}
+
+/// Replaces project `id`. The client must send the project's current ETag in `If-Match`.
+#[put("/projects/<id>", data = "<project>")]
+async fn update_project(
+    id: u32,
+    project: Json<ProjectBuilder>,
+    if_match: IfMatch,
+    author: Author,
+    mut db: Connection<BloggerDatabase>,
+) -> Result<Tagged, ApiError> {
+    require_if_match(&if_match)?;
+    let project = project.into_inner().id(id).bulid()?;
+    let result = project
+        .update_if_match(id, &if_match, &author.0, &mut db)
+        .await?;
+    if matches!(result, Conditional::Updated) {
+        after_write(&mut db).await;
+    }
+    conditional(project, result)
+}
+
+#[patch(
+    "/projects/<id>",
+    format = "application/merge-patch+json",
+    data = "<patch>"
+)]
+async fn merge_patch_project(
+    id: u32,
+    patch: Json<Value>,
+    if_match: IfMatch,
+    author: Author,
+    db: Connection<BloggerDatabase>,
+) -> Result<Tagged, ApiError> {
+    patch_project(id, Patch::Merge(patch.into_inner()), if_match, author, db).await
+}
+
+#[patch(
+    "/projects/<id>",
+    format = "application/json-patch+json",
+    data = "<patch>"
+)]
+async fn json_patch_project(
+    id: u32,
+    patch: Json<Value>,
+    if_match: IfMatch,
+    author: Author,
+    db: Connection<BloggerDatabase>,
+) -> Result<Tagged, ApiError> {
+    patch_project(id, Patch::Json(patch.into_inner()), if_match, author, db).await
+}
+
+async fn patch_project(
+    id: u32,
+    patch: Patch,
+    if_match: IfMatch,
+    author: Author,
+    mut db: Connection<BloggerDatabase>,
+) -> Result<Tagged, ApiError> {
+    require_if_match(&if_match)?;
+    let current = Project::get(id, &mut db)
+        .await?
+        .ok_or(ApiError::NotFound("Project"))?;
+    if !if_match.matches(&current.etag()) {
+        return Err(ApiError::PreconditionFailed(Box::new(current)));
+    }
+    let project = patch.apply(current).map_err(ApiError::Patch)?;
+    let result = project
+        .update_if_match(id, &if_match, &author.0, &mut db)
+        .await?;
+    if matches!(result, Conditional::Updated) {
+        after_write(&mut db).await;
+    }
+    conditional(project, result)
+}
+
+#[delete("/projects/<id>")]
+async fn delete_project(
+    id: u32,
+    _admin: Admin,
+    mut db: Connection<BloggerDatabase>,
+) -> Result<Status, ApiError> {
+    match Project::delete(id, &mut db).await? {
+        true => {
+            after_write(&mut db).await;
+            Ok(Status::NoContent)
+        }
+        false => Err(ApiError::NotFound("Project")),
+    }
+}
+
+/// Revisions of trashed projects are hidden like the projects themselves.
+async fn not_in_trash(id: u32, db: &mut MySqlConnection) -> Result<(), ApiError> {
+    match Project::in_trash(id, db).await? {
+        true => Err(ApiError::NotFound("Project")),
+        false => Ok(()),
+    }
+}
+
+#[get("/projects/<id>/revisions")]
+async fn list_revisions(
+    id: u32,
+    mut db: Connection<BloggerDatabase>,
+) -> Result<Json<Vec<RevisionSummary>>, ApiError> {
+    not_in_trash(id, &mut db).await?;
+    let revisions = Revision::list(id, &mut db).await?;
+    if revisions.is_empty() {
+        return Err(ApiError::NotFound("Project"));
+    }
+    Ok(Json(revisions))
+}
+
+#[get("/projects/<id>/revisions/<revision>")]
+async fn get_revision(
+    id: u32,
+    revision: u32,
+    mut db: Connection<BloggerDatabase>,
+) -> Result<Json<Revision>, ApiError> {
+    not_in_trash(id, &mut db).await?;
+    Revision::get(id, revision, &mut db)
+        .await?
+        .map(Json)
+        .ok_or(ApiError::NotFound("Project"))
+}
+
+/// Saves an old revision as the newest version of the project, recreating it if it was purged.
+/// Projects in the trash have to be restored from there first.
+#[post("/projects/<id>/revisions/<revision>/restore")]
+async fn restore_revision(
+    id: u32,
+    revision: u32,
+    author: Author,
+    mut db: Connection<BloggerDatabase>,
+) -> Result<Tagged, ApiError> {
+    not_in_trash(id, &mut db).await?;
+    let revision = Revision::get(id, revision, &mut db)
+        .await?
+        .ok_or(ApiError::NotFound("Project"))?;
+    let mut builder = revision.restore();
+    let project = builder.id(id).bulid()?;
+    if Project::get(id, &mut db).await?.is_some() {
+        project.update(id, &author.0, &mut db).await?;
+    } else {
+        project.insert(&author.0, &mut db).await?;
+    }
+    after_write(&mut db).await;
+    Ok(project.into())
+}
+
+#[get("/trash")]
+async fn list_trash(mut db: Connection<BloggerDatabase>) -> Result<Json<Trash>, ApiError> {
+    Ok(Json(Trash::get(&mut db).await?))
+}
+
+#[post("/trash/<id>/restore")]
+async fn restore_from_trash(
+    id: u32,
+    _admin: Admin,
+    mut db: Connection<BloggerDatabase>,
+) -> Result<Tagged, ApiError> {
+    if !Project::restore(id, &mut db).await? {
+        return Err(ApiError::NotFound("Project"));
+    }
+    after_write(&mut db).await;
+    Project::get(id, &mut db)
+        .await?
+        .map(Tagged::from)
+        .ok_or(ApiError::NotFound("Project"))
+}
+
+#[delete("/trash/<id>")]
+async fn purge_from_trash(
+    id: u32,
+    _admin: Admin,
+    mut db: Connection<BloggerDatabase>,
+) -> Result<Status, ApiError> {
+    match Project::purge(id, &mut db).await? {
+        true => Ok(Status::NoContent),
+        false => Err(ApiError::NotFound("Project")),
+    }
+}
+
+#[get("/tags")]
+async fn list_tags(mut db: Connection<BloggerDatabase>) -> Result<Json<Vec<TagNode>>, ApiError> {
+    Ok(Json(TagRegistry::load(&mut db).await?.tree()))
+}
+
+/// Looks up a tag by its canonical name or any alias, along with the tags under it.
+#[get("/tags/<name>")]
+async fn get_tag(
+    name: &str,
+    mut db: Connection<BloggerDatabase>,
+) -> Result<Json<TagNode>, ApiError> {
+    let registry = TagRegistry::load(&mut db).await?;
+    registry
+        .node(name)
+        .map(Json)
+        .ok_or(ApiError::NotFound("Tag"))
+}
+
+/// Creates or updates tag `name`, including its aliases and parent.
+#[put("/tags/<name>", data = "<tag>")]
+async fn save_tag(
+    name: &str,
+    tag: Json<Tag>,
+    _admin: Admin,
+    mut db: Connection<BloggerDatabase>,
+) -> Result<Json<Tag>, ApiError> {
+    let mut tag = tag.into_inner();
+    tag.name = name.trim().to_string();
+    tag.save(&mut db).await?;
+    after_write(&mut db).await;
+    Ok(Json(tag))
+}
+
+/// Renames, merges or deletes a tag across every project. With `?dry_run=true` nothing is saved.
+#[post("/tags/operations?<dry_run>", data = "<operation>")]
+async fn tag_operation(
+    operation: Json<TagOperation>,
+    dry_run: Option<bool>,
+    author: Author,
+    mut db: Connection<BloggerDatabase>,
+) -> Result<Json<TagReport>, ApiError> {
+    let report = operation
+        .apply(&author.0, dry_run.unwrap_or(false), &mut db)
+        .await?;
+    if !report.dry_run {
+        after_write(&mut db).await;
+    }
+    Ok(Json(report))
+}
+
+async fn load_tag_graph(db: &mut MySqlConnection) -> Result<TagGraph, ApiError> {
+    let projects = Projects::get(db).await?;
+    Ok(TagGraph::new(&projects.projects, &TagRegistry::global()))
+}
+
+/// How often tags appear on the same live project.
+#[get("/graph/tags")]
+async fn tag_graph(mut db: Connection<BloggerDatabase>) -> Result<Json<TagGraph>, ApiError> {
+    Ok(Json(load_tag_graph(&mut db).await?))
+}
+
+/// [`tag_graph`] as Graphviz DOT.
+#[get("/graph/tags.dot")]
+async fn tag_graph_dot(
+    mut db: Connection<BloggerDatabase>,
+) -> Result<(ContentType, String), ApiError> {
+    let graph = load_tag_graph(&mut db).await?;
+    Ok((TagGraph::dot_content_type(), graph.to_dot()))
+}
+
+/// Links and covers that were broken or permanently redirected when last checked.
+#[get("/links/health")]
+async fn link_health(mut db: Connection<BloggerDatabase>) -> Result<Json<HealthReport>, ApiError> {
+    Ok(Json(HealthReport::get(&mut db).await?))
+}
+
+/// Rewrites link and cover URLs across every project. With `?dry_run=true` nothing is saved.
+#[post("/links/rewrite?<dry_run>", data = "<rewrite>")]
+async fn rewrite_links(
+    rewrite: Json<LinkRewrite>,
+    dry_run: Option<bool>,
+    author: Author,
+    mut db: Connection<BloggerDatabase>,
+) -> Result<Json<RewriteReport>, ApiError> {
+    let report = rewrite
+        .apply(&author.0, dry_run.unwrap_or(false), &mut db)
+        .await?;
+    if !report.dry_run {
+        after_write(&mut db).await;
+    }
+    Ok(Json(report))
+}
+
+/// Full-text search over live projects, best matches first.
+#[get("/search?<q>&<limit>")]
+fn search_projects(q: &str, limit: Option<usize>) -> Json<Vec<SearchHit>> {
+    let limit = limit.unwrap_or(20).clamp(1, 100);
+    Json(SearchIndex::global().search(q, limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::{
+        figment::providers::{Format, Toml},
+        http::Header,
+        local::blocking::Client,
+    };
+
+    use super::*;
+
+    #[get("/author")]
+    fn author(author: Author) -> String {
+        author.0
+    }
+
+    fn client(toml: &str) -> Client {
+        let figment = rocket::Config::figment().merge(Toml::string(toml));
+        let rocket = rocket::custom(figment)
+            .attach(AdminToken)
+            .mount("/", routes![author]);
+        Client::tracked(rocket).unwrap()
+    }
+
+    fn get(client: &Client, headers: &[(&'static str, &'static str)]) -> (Status, String) {
+        let mut request = client.get("/author");
+        for &(name, value) in headers {
+            request.add_header(Header::new(name, value));
+        }
+        let response = request.dispatch();
+        (
+            response.status(),
+            response.into_string().unwrap_or_default(),
+        )
+    }
+
+    #[test]
+    fn admin_token() {
+        let admin = client("[api]\nadmin_token = \"secret\"");
+        assert_eq!(get(&admin, &[]).0, Status::Unauthorized);
+        assert_eq!(
+            get(&admin, &[("Authorization", "Bearer wrong")]).0,
+            Status::Unauthorized
+        );
+        assert_eq!(
+            get(
+                &admin,
+                &[("Authorization", "Bearer secret"), ("X-Author", "crystal")]
+            ),
+            (Status::Ok, "crystal".to_string())
+        );
+
+        let read_only = client("");
+        assert_eq!(
+            get(&read_only, &[("Authorization", "Bearer ")]).0,
+            Status::Unauthorized
+        );
+    }
+}
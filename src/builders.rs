@@ -1,20 +1,38 @@
-use itertools::Itertools;
+use std::{collections::HashSet, marker::PhantomData};
+
+use serde::{Deserialize, Serialize};
 use url::Url;
 
-use crate::{Link, LinkBuilderError, Project, ProjectBuilderError};
+use crate::{
+    Link, LinkBuilderError, Project, ProjectBuilderError,
+    links::LinkRole,
+    providers::{Provider, suggest_name},
+    tags::TagRegistry,
+    validation::ValidationPolicy,
+};
 
-#[derive(Default, Debug, Clone)]
+/// Also the shape [`Project`] is deserialized from, so JSON goes through the same rules.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ProjectBuilder {
+    id: Option<u32>,
     title: Option<String>,
     description: Option<String>,
     cover: Option<Url>,
     tags: Vec<String>,
     links: Vec<Link>,
+    featured: bool,
+    position: Option<u32>,
 }
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Deserialize)]
+#[serde(default)]
 pub struct LinkBuilder {
     name: Option<String>,
+    #[serde(rename = "link")]
     url: Option<Url>,
+    role: Option<LinkRole>,
+    order: Option<u32>,
+    primary: bool,
 }
 
 impl ProjectBuilder {
@@ -22,6 +40,10 @@ impl ProjectBuilder {
         Self::default()
     }
 
+    pub fn id(&mut self, id: u32) -> &mut Self {
+        _ = self.id.replace(id);
+        self
+    }
     pub fn title(&mut self, title: &str) -> &mut Self {
         _ = self.title.replace(title.to_string());
         self
@@ -34,8 +56,28 @@ impl ProjectBuilder {
         _ = self.cover.replace(cover_link);
         self
     }
+    pub fn remove_cover(&mut self) -> &mut Self {
+        _ = self.cover.take();
+        self
+    }
+    pub fn featured(&mut self, featured: bool) -> &mut Self {
+        self.featured = featured;
+        self
+    }
+    pub fn position(&mut self, position: u32) -> &mut Self {
+        _ = self.position.replace(position);
+        self
+    }
+    pub fn remove_position(&mut self) -> &mut Self {
+        _ = self.position.take();
+        self
+    }
+    /// Adds `tag` under its canonical name from the [`TagRegistry`], unless it is already there.
     pub fn add_tag(&mut self, tag: &str) -> &mut Self {
-        self.tags.push(tag.to_string());
+        let tag = TagRegistry::global().resolve(tag);
+        if !self.tags.contains(&tag) {
+            self.tags.push(tag);
+        }
         self
     }
     pub fn add_link(&mut self, link: Link) -> &mut Self {
@@ -43,7 +85,8 @@ impl ProjectBuilder {
         self
     }
     pub fn remove_tag(&mut self, tag_name: &str) -> &mut Self {
-        self.tags.retain(|tag| tag != tag_name);
+        let tag_name = TagRegistry::global().resolve(tag_name);
+        self.tags.retain(|tag| *tag != tag_name);
         self
     }
     pub fn remove_link(&mut self, link_name: &str) -> &mut Self {
@@ -51,35 +94,152 @@ impl ProjectBuilder {
         self
     }
 
+    /// A valid project for tests with `tags` and the [`LinkBuilder::sample`] link.
+    #[cfg(test)]
+    pub(crate) fn sample(tags: &[&str]) -> Self {
+        let mut builder = Self::new();
+        builder
+            .title("Project")
+            .description("Description")
+            .add_link(LinkBuilder::sample());
+        for tag in tags {
+            builder.add_tag(tag);
+        }
+        builder
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+    pub fn links(&self) -> &[Link] {
+        &self.links
+    }
+
+    /// Every rule of the global [`ValidationPolicy`] the builder currently breaks, in the order
+    /// [`ProjectBuilder::bulid`] checks them.
+    pub fn validate(&self) -> Vec<ProjectBuilderError> {
+        self.validate_with(ValidationPolicy::global())
+    }
+    pub fn validate_with(&self, policy: &ValidationPolicy) -> Vec<ProjectBuilderError> {
+        self.validate_in(policy, &TagRegistry::global())
+    }
+    fn validate_in(
+        &self,
+        policy: &ValidationPolicy,
+        registry: &TagRegistry,
+    ) -> Vec<ProjectBuilderError> {
+        let mut errors = vec![];
+        match &self.title {
+            None => errors.push(ProjectBuilderError::Title),
+            Some(title) if !policy.title_ok(title) => errors.push(ProjectBuilderError::TitleLength),
+            Some(_) => {}
+        }
+        match &self.description {
+            None => errors.push(ProjectBuilderError::Description),
+            Some(description) if !policy.description_ok(description) => {
+                errors.push(ProjectBuilderError::DescriptionLength)
+            }
+            Some(_) => {}
+        }
+        if policy.require_cover && self.cover.is_none() {
+            errors.push(ProjectBuilderError::Cover);
+        }
+        let tags = self.resolved_tags(registry);
+        if tags.len() < policy.tag_count_min {
+            errors.push(ProjectBuilderError::Tags);
+        }
+        let mut unique = HashSet::new();
+        if !tags.iter().all(|tag| unique.insert(tag)) {
+            errors.push(ProjectBuilderError::DuplicateTags);
+        }
+        if policy.tag_count_max.is_some_and(|max| tags.len() > max) {
+            errors.push(ProjectBuilderError::TooManyTags);
+        }
+        if !tags.iter().all(|tag| policy.tag_ok(tag)) {
+            errors.push(ProjectBuilderError::TagLength);
+        }
+        if !tags.iter().all(|tag| policy.tag_allowed(tag)) {
+            errors.push(ProjectBuilderError::UnknownTag);
+        }
+        if self.links.len() < policy.link_count_min {
+            errors.push(ProjectBuilderError::Links);
+        }
+        if policy
+            .link_count_max
+            .is_some_and(|max| self.links.len() > max)
+        {
+            errors.push(ProjectBuilderError::TooManyLinks);
+        }
+        let mut urls = HashSet::new();
+        if policy.unique_links
+            && !self
+                .links
+                .iter()
+                .all(|link| urls.insert(policy.canonicalize(&link.link)))
+        {
+            errors.push(ProjectBuilderError::DuplicateLinks);
+        }
+        if self.links.iter().filter(|link| link.primary).count() > 1 {
+            errors.push(ProjectBuilderError::PrimaryLinks);
+        }
+        let mut names = HashSet::new();
+        if policy.unique_link_names && !self.links.iter().all(|link| names.insert(&link.name)) {
+            errors.push(ProjectBuilderError::DuplicateLinkNames);
+        }
+        errors
+    }
+
+    /// The tags under their canonical names, since they may have been set without
+    /// [`ProjectBuilder::add_tag`], e.g. when deserialized.
+    fn resolved_tags(&self, registry: &TagRegistry) -> Vec<String> {
+        self.tags.iter().map(|tag| registry.resolve(tag)).collect()
+    }
+
     pub fn bulid(&self) -> Result<Project, ProjectBuilderError> {
+        self.bulid_with(ValidationPolicy::global())
+    }
+    /// Link and cover URLs are canonicalized here, and only here, so they always follow `policy`.
+    pub fn bulid_with(&self, policy: &ValidationPolicy) -> Result<Project, ProjectBuilderError> {
+        self.bulid_with_registry(policy, &TagRegistry::global())
+    }
+    /// Like [`ProjectBuilder::bulid_with`], resolving tags through `registry` instead of the
+    /// global one.
+    pub fn bulid_with_registry(
+        &self,
+        policy: &ValidationPolicy,
+        registry: &TagRegistry,
+    ) -> Result<Project, ProjectBuilderError> {
+        if let Some(error) = self.validate_in(policy, registry).into_iter().next() {
+            return Err(error);
+        }
         let Self {
+            id,
             title,
             description,
             cover,
-            tags,
+            tags: _,
             links,
+            featured,
+            position,
         } = self;
-        if title.is_some() && description.is_some() && !tags.is_empty() && !links.is_empty() {
-            Ok(Project {
-                title: title.as_ref().unwrap().clone(),
-                description: description.as_ref().unwrap().clone(),
-                cover: cover.clone(),
-                tags: tags.clone(),
-                links: links.clone(),
+        let mut links = links
+            .iter()
+            .map(|link| Link {
+                link: policy.canonicalize(&link.link),
+                ..link.clone()
             })
-        } else if title.is_none() {
-            Err(ProjectBuilderError::Title)
-        } else if description.is_none() {
-            Err(ProjectBuilderError::Description)
-        } else if cover.is_none() {
-            Err(ProjectBuilderError::Cover)
-        } else if tags.is_empty() {
-            Err(ProjectBuilderError::Tags)
-        } else if links.is_empty() {
-            Err(ProjectBuilderError::Links)
-        } else {
-            unreachable!()
-        }
+            .collect::<Vec<_>>();
+        links.sort_by_key(|link| link.order.unwrap_or(u32::MAX));
+        Ok(Project {
+            id: *id,
+            title: title.as_ref().unwrap().clone(),
+            description: description.as_ref().unwrap().clone(),
+            cover: cover.as_ref().map(|cover| policy.canonicalize(cover)),
+            tags: self.resolved_tags(registry),
+            links,
+            featured: *featured,
+            position: *position,
+        })
     }
 }
 
@@ -91,6 +251,10 @@ impl LinkBuilder {
         Link {
             name: "Example".to_string(),
             link: Url::parse("https://example.com").unwrap(),
+            role: None,
+            order: None,
+            primary: false,
+            provider: None,
         }
     }
 
@@ -102,25 +266,151 @@ impl LinkBuilder {
         _ = self.url.replace(url);
         self
     }
+    pub fn role(&mut self, role: LinkRole) -> &mut Self {
+        _ = self.role.replace(role);
+        self
+    }
+    pub fn order(&mut self, order: u32) -> &mut Self {
+        _ = self.order.replace(order);
+        self
+    }
+    pub fn primary(&mut self, primary: bool) -> &mut Self {
+        self.primary = primary;
+        self
+    }
 
     pub fn bulid(&self) -> Result<Link, LinkBuilderError> {
-        match self {
-            LinkBuilder {
-                name: Some(name),
-                url: Some(url),
-            } if !name.is_empty() => Ok(Link {
+        self.bulid_with(ValidationPolicy::global())
+    }
+    /// Without a name, the link is named after its [`Provider`] or host.
+    pub fn bulid_with(&self, policy: &ValidationPolicy) -> Result<Link, LinkBuilderError> {
+        let suggested = match (&self.name, &self.url) {
+            (None, Some(url)) => suggest_name(url),
+            _ => None,
+        };
+        match (self.name.as_ref().or(suggested.as_ref()), &self.url) {
+            (None, _) => Err(LinkBuilderError::Name),
+            (Some(name), _) if name.is_empty() => Err(LinkBuilderError::Name),
+            (_, None) => Err(LinkBuilderError::Url),
+            (Some(name), _) if !policy.link_name_ok(name) => Err(LinkBuilderError::NameLength),
+            (_, Some(url)) if !policy.scheme_allowed(url) => Err(LinkBuilderError::Scheme),
+            (_, Some(url)) if !policy.host_allowed(url) => Err(LinkBuilderError::Host),
+            (Some(name), Some(url)) => Ok(Link {
                 name: name.clone(),
                 link: url.clone(),
+                role: self.role,
+                order: self.order,
+                primary: self.primary,
+                provider: Provider::classify(url),
             }),
-            LinkBuilder {
-                name: Some(name), ..
-            } if name.is_empty() => Err(LinkBuilderError::Name),
-            LinkBuilder { name: None, .. } => Err(LinkBuilderError::Name),
-            LinkBuilder { url: None, .. } => Err(LinkBuilderError::Url),
-            _ => unreachable!(),
         }
     }
 }
+
+/// Typestate marker for a required field that has not been set yet.
+#[derive(Debug, Clone, Copy)]
+pub struct Unset;
+/// Typestate marker for a required field that has been set.
+#[derive(Debug, Clone, Copy)]
+pub struct Set;
+
+/// A [`ProjectBuilder`] that tracks the required fields in its type, so `build()` only exists
+/// once the title, description, a tag and a link have all been given. The rest of the
+/// [`ValidationPolicy`] is still checked when building.
+///
+/// ```
+/// use blogger::{Project, builders::LinkBuilder};
+///
+/// let project = Project::builder()
+///     .title("blogger")
+///     .description("portfolio backend")
+///     .add_tag("Rust")
+///     .add_link(LinkBuilder::sample())
+///     .build()
+///     .unwrap();
+/// ```
+///
+/// ```compile_fail
+/// use blogger::{Project, builders::LinkBuilder};
+///
+/// let project = Project::builder()
+///     .title("blogger")
+///     .add_tag("Rust")
+///     .add_link(LinkBuilder::sample())
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct TypedProjectBuilder<Title = Unset, Description = Unset, Tags = Unset, Links = Unset> {
+    builder: ProjectBuilder,
+    state: PhantomData<(Title, Description, Tags, Links)>,
+}
+
+impl Project {
+    pub fn builder() -> TypedProjectBuilder {
+        TypedProjectBuilder {
+            builder: ProjectBuilder::new(),
+            state: PhantomData,
+        }
+    }
+}
+
+impl<T, D, G, L> TypedProjectBuilder<T, D, G, L> {
+    fn into_state<T2, D2, G2, L2>(self) -> TypedProjectBuilder<T2, D2, G2, L2> {
+        TypedProjectBuilder {
+            builder: self.builder,
+            state: PhantomData,
+        }
+    }
+
+    pub fn id(mut self, id: u32) -> Self {
+        self.builder.id(id);
+        self
+    }
+    pub fn title(mut self, title: &str) -> TypedProjectBuilder<Set, D, G, L> {
+        self.builder.title(title);
+        self.into_state()
+    }
+    pub fn description(mut self, description: &str) -> TypedProjectBuilder<T, Set, G, L> {
+        self.builder.description(description);
+        self.into_state()
+    }
+    pub fn cover(mut self, cover_link: Url) -> Self {
+        self.builder.cover(cover_link);
+        self
+    }
+    pub fn featured(mut self, featured: bool) -> Self {
+        self.builder.featured(featured);
+        self
+    }
+    pub fn add_tag(mut self, tag: &str) -> TypedProjectBuilder<T, D, Set, L> {
+        self.builder.add_tag(tag);
+        self.into_state()
+    }
+    pub fn add_link(mut self, link: Link) -> TypedProjectBuilder<T, D, G, Set> {
+        self.builder.add_link(link);
+        self.into_state()
+    }
+}
+
+impl TypedProjectBuilder<Set, Set, Set, Set> {
+    pub fn build(self) -> Result<Project, ProjectBuilderError> {
+        self.builder.bulid()
+    }
+}
+
+impl TryFrom<ProjectBuilder> for Project {
+    type Error = ProjectBuilderError;
+    fn try_from(builder: ProjectBuilder) -> Result<Self, Self::Error> {
+        builder.bulid()
+    }
+}
+impl TryFrom<LinkBuilder> for Link {
+    type Error = LinkBuilderError;
+    fn try_from(builder: LinkBuilder) -> Result<Self, Self::Error> {
+        builder.bulid()
+    }
+}
+
 pub trait Edit {
     type Builder;
     fn edit(self) -> Self::Builder;
@@ -130,11 +420,14 @@ impl Edit for Project {
     type Builder = ProjectBuilder;
     fn edit(self) -> Self::Builder {
         ProjectBuilder {
+            id: self.id,
             title: Some(self.title),
             description: Some(self.description),
             cover: self.cover,
             tags: self.tags,
             links: self.links,
+            featured: self.featured,
+            position: self.position,
         }
     }
 }
@@ -145,6 +438,9 @@ impl Edit for Link {
         LinkBuilder {
             name: Some(self.name),
             url: Some(self.link),
+            role: self.role,
+            order: self.order,
+            primary: self.primary,
         }
     }
 }
@@ -0,0 +1,260 @@
+//! Maintenance commands run with `blogger <command>` instead of starting the server.
+use std::path::{Path, PathBuf};
+
+use rocket::figment::Figment;
+use rocket_db_pools::sqlx::{Connection, MySqlConnection};
+
+use crate::{
+    CliError, Project, Projects, SyncError, content,
+    health::{self, Checker, HealthConfig, HealthReport, LinkCheck, UnhealthyLink},
+    rewrite::{LinkRewrite, RewriteRule},
+    sync::{Direction, SyncAction, SyncPlan},
+    tags::{TagOperation, TagRegistry},
+    trash::{Trash, TrashConfig, TrashedProject},
+    validation::ValidationPolicy,
+};
+
+pub const USAGE: &str = "\
+blogger sync <push|pull> [--dry-run] [--dir <path>]
+       blogger trash <list | restore <id> | purge <id> | purge --expired>
+       blogger tags <rename <from> <to> | merge <into> <from>... | delete <tag>> [--dry-run]
+       blogger links <check | report | rewrite <host|prefix> <from> <to> [--dry-run]>";
+
+#[derive(Debug, Default)]
+struct Flags {
+    dry_run: bool,
+    dir: Option<PathBuf>,
+}
+
+fn parse_flags(args: &[String]) -> Result<Flags, CliError> {
+    let mut flags = Flags::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" => flags.dry_run = true,
+            "--dir" => flags.dir = Some(args.next().ok_or(CliError::Usage(USAGE))?.into()),
+            _ => return Err(CliError::Usage(USAGE)),
+        }
+    }
+    Ok(flags)
+}
+
+async fn connect(figment: &Figment) -> Result<MySqlConnection, CliError> {
+    let url: String = figment
+        .extract_inner("databases.blogger.url")
+        .map_err(Box::new)?;
+    let mut db = MySqlConnection::connect(&url).await?;
+    TagRegistry::reload(&mut db).await?;
+    Ok(db)
+}
+
+pub async fn run(args: &[String]) -> Result<(), CliError> {
+    let figment = rocket::Config::figment();
+    ValidationPolicy::from_figment(&figment)?.install();
+    match args {
+        [command, direction, rest @ ..] if command == "sync" => {
+            let direction = match direction.as_str() {
+                "push" => Direction::ToDatabase,
+                "pull" => Direction::ToDirectory,
+                _ => return Err(CliError::Usage(USAGE)),
+            };
+            let flags = parse_flags(rest)?;
+            let dir = match flags.dir {
+                Some(dir) => dir,
+                None => figment.extract_inner("content.dir").map_err(Box::new)?,
+            };
+            sync(&figment, direction, &dir, flags.dry_run).await
+        }
+        [command, rest @ ..] if command == "trash" => trash(&figment, rest).await,
+        [command, rest @ ..] if command == "tags" => tags(&figment, rest).await,
+        [command, rest @ ..] if command == "links" => links(&figment, rest).await,
+        _ => Err(CliError::Usage(USAGE)),
+    }
+}
+
+fn parse_id(arg: &str) -> Result<u32, CliError> {
+    arg.parse().map_err(|_| CliError::Usage(USAGE))
+}
+
+async fn sync(
+    figment: &Figment,
+    direction: Direction,
+    dir: &Path,
+    dry_run: bool,
+) -> Result<(), CliError> {
+    let mut db = connect(figment).await?;
+    let database = Projects::get(&mut db).await?;
+    let trashed = Trash::ids(&mut db).await?;
+    let directory = content::load_dir(dir)?;
+    let plan =
+        SyncPlan::new(&database, &trashed, &directory, direction).map_err(SyncError::from)?;
+    print!("{plan}");
+    if !dry_run && !plan.is_empty() {
+        plan.apply(&mut db, dir).await?;
+        let skipped = plan
+            .actions
+            .iter()
+            .filter(|action| matches!(action, SyncAction::Skip { .. }))
+            .count();
+        println!("Applied {} change(s).", plan.actions.len() - skipped);
+    }
+    Ok(())
+}
+
+async fn trash(figment: &Figment, args: &[String]) -> Result<(), CliError> {
+    let mut db = connect(figment).await?;
+    match args {
+        [command] if command == "list" => {
+            let trash = Trash::get(&mut db).await?;
+            if trash.projects.is_empty() {
+                println!("The trash is empty.");
+            }
+            for TrashedProject {
+                project,
+                deleted_at,
+            } in trash.projects
+            {
+                println!(
+                    "#{} {:?} (deleted at {deleted_at})",
+                    project.id.unwrap_or_default(),
+                    project.title
+                );
+            }
+        }
+        [command, id] if command == "restore" => {
+            let id = parse_id(id)?;
+            if Project::restore(id, &mut db).await? {
+                println!("Restored #{id}.");
+            } else {
+                println!("#{id} is not in the trash.");
+            }
+        }
+        [command, flag] if command == "purge" && flag == "--expired" => {
+            let config = TrashConfig::from_figment(figment)?;
+            let purged = Trash::purge_expired(config.retention(), &mut db).await?;
+            println!("Purged {purged} project(s).");
+        }
+        [command, id] if command == "purge" => {
+            let id = parse_id(id)?;
+            if Project::purge(id, &mut db).await? {
+                println!("Purged #{id}.");
+            } else {
+                println!("#{id} is not in the trash.");
+            }
+        }
+        _ => return Err(CliError::Usage(USAGE)),
+    }
+    Ok(())
+}
+
+async fn tags(figment: &Figment, args: &[String]) -> Result<(), CliError> {
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    let args: Vec<String> = args
+        .iter()
+        .filter(|arg| *arg != "--dry-run")
+        .cloned()
+        .collect();
+    let operation = match args.as_slice() {
+        [command, from, to] if command == "rename" => TagOperation::Rename {
+            from: from.clone(),
+            to: to.clone(),
+        },
+        [command, into, from @ ..] if command == "merge" && !from.is_empty() => {
+            TagOperation::Merge {
+                from: from.to_vec(),
+                into: into.clone(),
+            }
+        }
+        [command, tag] if command == "delete" => TagOperation::Delete { tag: tag.clone() },
+        _ => return Err(CliError::Usage(USAGE)),
+    };
+    let mut db = connect(figment).await?;
+    let report = operation.apply("cli", dry_run, &mut db).await?;
+    println!(
+        "{operation}: {} project(s) affected {:?}",
+        report.affected, report.projects
+    );
+    if dry_run {
+        println!("Dry run, nothing was changed.");
+    }
+    Ok(())
+}
+
+async fn links(figment: &Figment, args: &[String]) -> Result<(), CliError> {
+    if let [command, rest @ ..] = args
+        && command == "rewrite"
+    {
+        return rewrite(figment, rest).await;
+    }
+    let mut db = connect(figment).await?;
+    match args {
+        [command] if command == "check" => {
+            let checker = Checker::new(&HealthConfig::from_figment(figment)?)?;
+            let projects = Projects::get(&mut db).await?;
+            let checks = checker.check_all(health::urls(&projects.projects)).await;
+            LinkCheck::save(&checks, &mut db).await?;
+            println!("Checked {} link(s).", checks.len());
+        }
+        [command] if command == "report" => {}
+        _ => return Err(CliError::Usage(USAGE)),
+    }
+    let report = HealthReport::get(&mut db).await?;
+    if report.links.is_empty() {
+        println!("Every checked link is healthy.");
+    }
+    for UnhealthyLink {
+        project,
+        title,
+        link,
+        problem,
+        check,
+    } in report.links
+    {
+        let link = link.map_or("cover".to_string(), |name| format!("{name:?}"));
+        let outcome = match (&check.error, check.status, &check.final_url) {
+            (Some(error), ..) => error.clone(),
+            (None, Some(status), Some(to)) => format!("{status} after redirects to {to}"),
+            (None, Some(status), None) => status.to_string(),
+            (None, None, _) => "no response".to_string(),
+        };
+        println!(
+            "#{project} {title:?} {link}: {problem:?} <{}> ({outcome})",
+            check.url
+        );
+    }
+    Ok(())
+}
+
+async fn rewrite(figment: &Figment, args: &[String]) -> Result<(), CliError> {
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    let args: Vec<String> = args
+        .iter()
+        .filter(|arg| *arg != "--dry-run")
+        .cloned()
+        .collect();
+    let rule = match args.as_slice() {
+        [by, from, to] if by == "host" => RewriteRule::Host {
+            from: from.clone(),
+            to: to.clone(),
+        },
+        [by, from, to] if by == "prefix" => RewriteRule::Prefix {
+            from: from.clone(),
+            to: to.clone(),
+        },
+        _ => return Err(CliError::Usage(USAGE)),
+    };
+    let mut db = connect(figment).await?;
+    let rewrite = LinkRewrite { rules: vec![rule] };
+    let report = rewrite.apply("cli", dry_run, &mut db).await?;
+    print!("{report}");
+    println!(
+        "{} URL(s) in {} project(s) rewritten {:?}",
+        report.changes.len(),
+        report.projects.len(),
+        report.projects
+    );
+    if dry_run {
+        println!("Dry run, nothing was changed.");
+    }
+    Ok(())
+}
@@ -0,0 +1,446 @@
+//! Directory-of-files content mode.
+//!
+//! Every `.json` or `.md` file in the content directory holds one project. Markdown files start
+//! with a TOML front matter block fenced by `+++` lines, and the body becomes the description:
+//!
+//! ```text
+//! +++
+//! title = "blogger"
+//! tags = ["Rust"]
+//! links = [{ name = "Source", link = "https://github.com/itscrystalline/blogger" }]
+//! +++
+//! A tiny portfolio backend.
+//! ```
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use itertools::Itertools;
+use rocket::{
+    Build, Orbit, Rocket, State,
+    fairing::{self, Fairing, Info, Kind},
+    figment::Figment,
+    get, routes,
+    serde::json::Json,
+    tokio,
+};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{ContentError, Link, Project, Projects, builders::ProjectBuilder};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FrontMatter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<u32>,
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cover: Option<Url>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    featured: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    position: Option<u32>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    links: Vec<Link>,
+}
+
+pub fn is_content_file(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("json" | "md")
+    )
+}
+
+pub fn parse_markdown(source: &str) -> Result<Project, ContentError> {
+    let rest = source
+        .trim_start()
+        .strip_prefix("+++")
+        .ok_or(ContentError::FrontMatter)?;
+    let (front, body) = rest.split_once("\n+++").ok_or(ContentError::FrontMatter)?;
+    let body = body.split_once('\n').map_or("", |(_, body)| body);
+    let front: FrontMatter = toml::from_str(front)?;
+
+    let mut builder = ProjectBuilder::new();
+    if let Some(id) = front.id {
+        builder.id(id);
+    }
+    if let Some(title) = &front.title {
+        builder.title(title);
+    }
+    if !body.trim().is_empty() {
+        builder.description(body.trim());
+    }
+    if let Some(cover) = front.cover {
+        builder.cover(cover);
+    }
+    builder.featured(front.featured);
+    if let Some(position) = front.position {
+        builder.position(position);
+    }
+    for tag in &front.tags {
+        builder.add_tag(tag);
+    }
+    for link in front.links {
+        builder.add_link(link);
+    }
+    Ok(builder.bulid()?)
+}
+
+pub fn parse_json(source: &str) -> Result<Project, ContentError> {
+    Ok(serde_json::from_str(source)?)
+}
+
+pub fn load_file(path: &Path) -> Result<Project, ContentError> {
+    let source = fs::read_to_string(path)?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("md") => parse_markdown(&source),
+        Some("json") => parse_json(&source),
+        _ => Err(ContentError::Unsupported),
+    }
+}
+
+pub fn to_markdown(project: &Project) -> Result<String, ContentError> {
+    let front = FrontMatter {
+        id: project.id,
+        title: Some(project.title.clone()),
+        cover: project.cover.clone(),
+        featured: project.featured,
+        position: project.position,
+        tags: project.tags.clone(),
+        links: project.links.clone(),
+    };
+    Ok(format!(
+        "+++\n{}+++\n{}\n",
+        toml::to_string(&front)?,
+        project.description
+    ))
+}
+
+/// Renders `project` in the format implied by the extension of `path`.
+pub fn render(path: &Path, project: &Project) -> Result<String, ContentError> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("md") => to_markdown(project),
+        Some("json") => Ok(serde_json::to_string_pretty(project)?),
+        _ => Err(ContentError::Unsupported),
+    }
+}
+
+/// Loads every content file in `dir`, sorted by path, failing on the first invalid one.
+pub fn load_dir(dir: &Path) -> Result<Vec<(PathBuf, Project)>, ContentError> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .filter_ok(|path| is_content_file(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| match load_file(&path) {
+            Ok(project) => Ok((path, project)),
+            Err(source) => Err(ContentError::InFile {
+                path,
+                source: Box::new(source),
+            }),
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+struct Entry {
+    modified: SystemTime,
+    /// Last version of the file that passed validation, if there ever was one.
+    project: Option<Project>,
+}
+
+/// In-memory view of a content directory that can be refreshed while readers hold on to older
+/// snapshots.
+#[derive(Debug)]
+pub struct ContentStore {
+    dir: PathBuf,
+    files: Mutex<BTreeMap<PathBuf, Entry>>,
+    current: RwLock<Arc<Projects>>,
+}
+
+impl ContentStore {
+    pub fn open(dir: impl Into<PathBuf>) -> Self {
+        let store = ContentStore {
+            dir: dir.into(),
+            files: Mutex::default(),
+            current: RwLock::new(Arc::new(Projects { projects: vec![] })),
+        };
+        store.refresh();
+        store
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+    pub fn projects(&self) -> Arc<Projects> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Reloads every file whose modification time changed since the last call and swaps in a new
+    /// [`Projects`] if anything did. A file that fails to load keeps its previous good version.
+    pub fn refresh(&self) -> bool {
+        let read_dir = match fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(e) => {
+                log::error!("cannot read content directory {}: {e}", self.dir.display());
+                return false;
+            }
+        };
+        let mut files = self.files.lock().unwrap();
+        let mut seen = HashSet::new();
+        let mut changed = false;
+
+        for path in read_dir.flatten().map(|entry| entry.path()) {
+            if !is_content_file(&path) {
+                continue;
+            }
+            let Ok(modified) = fs::metadata(&path).and_then(|meta| meta.modified()) else {
+                continue;
+            };
+            seen.insert(path.clone());
+            if files
+                .get(&path)
+                .is_some_and(|entry| entry.modified == modified)
+            {
+                continue;
+            }
+            let previous = files.remove(&path).and_then(|entry| entry.project);
+            let project = match load_file(&path) {
+                Ok(project) => {
+                    log::info!("loaded {}", path.display());
+                    changed = true;
+                    Some(project)
+                }
+                Err(e) => {
+                    log::error!("{}: {e}, keeping previous version", path.display());
+                    previous
+                }
+            };
+            files.insert(path, Entry { modified, project });
+        }
+
+        let before = files.len();
+        files.retain(|path, _| seen.contains(path));
+        changed |= files.len() != before;
+
+        if changed {
+            // Listed like the database does, featured first; files without a position stay in
+            // path order.
+            let mut projects = files
+                .values()
+                .filter_map(|entry| entry.project.clone())
+                .collect::<Vec<_>>();
+            projects
+                .sort_by_key(|project| (!project.featured, project.position.unwrap_or(u32::MAX)));
+            *self.current.write().unwrap() = Arc::new(Projects { projects });
+        }
+        changed
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentConfig {
+    dir: PathBuf,
+    #[serde(default = "ContentConfig::default_poll_ms")]
+    poll_ms: u64,
+}
+impl ContentConfig {
+    fn default_poll_ms() -> u64 {
+        500
+    }
+
+    /// Reads `[content]`, or `None` if the section is missing.
+    fn from_figment(figment: &Figment) -> Result<Option<Self>, Box<rocket::figment::Error>> {
+        if figment.contains("content") {
+            figment.extract_inner("content").map(Some).map_err(Box::new)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+struct PollInterval(Duration);
+
+/// Serves the `[content]` directory from `Rocket.toml` under `/preview` and polls it for changes.
+/// Does nothing when no content directory is configured.
+pub struct ContentDir;
+
+#[rocket::async_trait]
+impl Fairing for ContentDir {
+    fn info(&self) -> Info {
+        Info {
+            name: "Content Directory",
+            kind: Kind::Ignite | Kind::Liftoff,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        let config = match ContentConfig::from_figment(rocket.figment()) {
+            Ok(Some(config)) => config,
+            Ok(None) => return Ok(rocket),
+            Err(e) => {
+                log::error!("invalid [content] config: {e}");
+                return Err(rocket);
+            }
+        };
+        let store = Arc::new(ContentStore::open(config.dir));
+        Ok(rocket
+            .manage(store)
+            .manage(PollInterval(Duration::from_millis(config.poll_ms)))
+            .mount("/preview", routes![preview_projects]))
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let (Some(store), Some(PollInterval(poll))) = (
+            rocket.state::<Arc<ContentStore>>(),
+            rocket.state::<PollInterval>(),
+        ) else {
+            return;
+        };
+        let store = store.clone();
+        let mut interval = tokio::time::interval(*poll);
+        tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+                let store = store.clone();
+                _ = tokio::task::spawn_blocking(move || store.refresh()).await;
+            }
+        });
+    }
+}
+
+#[get("/projects")]
+fn preview_projects(store: &State<Arc<ContentStore>>) -> Json<Projects> {
+    Json(store.projects().as_ref().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use super::*;
+    use crate::builders::Edit;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("blogger-{name}-{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+    fn write(path: &Path, contents: &str, modified: u64) {
+        fs::write(path, contents).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(modified))
+            .unwrap();
+    }
+
+    const MARKDOWN: &str = r#"+++
+title = "blogger"
+tags = ["Rust"]
+links = [{ name = "Example", link = "https://example.com" }]
++++
+A tiny portfolio backend.
+"#;
+
+    #[test]
+    fn markdown_project() {
+        let project = parse_markdown(MARKDOWN).unwrap();
+        assert_eq!(project.title, "blogger");
+        assert_eq!(project.description, "A tiny portfolio backend.");
+        assert_eq!(project.tags, vec!["Rust"]);
+        assert_eq!(project.links[0].name, "Example");
+    }
+    #[test]
+    fn markdown_without_front_matter() {
+        assert!(matches!(
+            parse_markdown("just text"),
+            Err(ContentError::FrontMatter)
+        ));
+        assert!(matches!(
+            parse_markdown("+++\ntitle = \"a\"\n+++\n"),
+            Err(ContentError::Project(
+                crate::ProjectBuilderError::Description
+            ))
+        ));
+    }
+    #[test]
+    fn markdown_round_trip() {
+        let mut builder = parse_markdown(MARKDOWN).unwrap().edit();
+        builder.id(7).featured(true).position(3);
+        let project = builder.bulid().unwrap();
+        let rendered = to_markdown(&project).unwrap();
+        assert!(rendered.starts_with("+++\nid = 7\n"));
+        assert!(rendered.contains("\nposition = 3\n"));
+        assert_eq!(parse_markdown(&rendered).unwrap(), project);
+    }
+    #[test]
+    fn reload_keeps_last_good_version() {
+        let dir = temp_dir("reload");
+        let file = dir.join("blogger.md");
+        write(&file, MARKDOWN, 1);
+        write(&dir.join("notes.txt"), "ignored", 1);
+
+        let store = ContentStore::open(&dir);
+        let first = store.projects();
+        assert_eq!(first.projects.len(), 1);
+        assert!(!store.refresh());
+
+        write(&file, &MARKDOWN.replace("blogger", "renamed"), 2);
+        assert!(store.refresh());
+        assert_eq!(store.projects().projects[0].title, "renamed");
+        assert_eq!(first.projects[0].title, "blogger");
+
+        write(&file, "+++\ntitle = \"broken\"\n+++\n", 3);
+        assert!(!store.refresh());
+        assert_eq!(store.projects().projects[0].title, "renamed");
+
+        fs::remove_file(&file).unwrap();
+        assert!(store.refresh());
+        assert!(store.projects().projects.is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+    #[test]
+    fn featured_first() {
+        let dir = temp_dir("featured");
+        write(&dir.join("a.md"), MARKDOWN, 1);
+        write(
+            &dir.join("b.md"),
+            &MARKDOWN.replace(
+                "+++\ntitle = \"blogger\"",
+                "+++\ntitle = \"featured\"\nfeatured = true",
+            ),
+            1,
+        );
+        let store = ContentStore::open(&dir);
+        let titles = store
+            .projects()
+            .projects
+            .iter()
+            .map(|project| project.title.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(titles, ["featured", "blogger"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+    #[test]
+    fn config() {
+        use rocket::figment::providers::{Format, Toml};
+
+        let config = |toml: &str| ContentConfig::from_figment(&Figment::from(Toml::string(toml)));
+        let content = config("[content]\ndir = \"content\"").unwrap().unwrap();
+        assert_eq!(content.dir, PathBuf::from("content"));
+        assert_eq!(content.poll_ms, 500);
+        assert!(config("").unwrap().is_none());
+        assert!(config("[content]\npoll_ms = 100").is_err());
+    }
+}
@@ -1,52 +1,264 @@
+use itertools::Itertools;
 use rocket_db_pools::{
-    Connection, Database,
-    sqlx::{self, Result, Row, mysql::MySqlRow},
+    Database,
+    sqlx::{self, Connection, MySql, MySqlConnection, QueryBuilder, Result, Row, mysql::MySqlRow},
 };
 use url::Url;
 
 use crate::{
-    Link, Project, Projects,
+    Project, Projects,
     builders::{LinkBuilder, ProjectBuilder},
+    links::LinkRole,
+    revisions::Revision,
+    validation::ValidationPolicy,
 };
 
 #[derive(Database)]
 #[database("blogger")]
 pub struct BloggerDatabase(sqlx::MySqlPool);
 
-impl Projects {
-    pub async fn get(mut db: Connection<BloggerDatabase>) -> Result<Projects> {
-        let mut projects_incomplete = sqlx::query("SELECT * FROM projects")
-            .map(|row: MySqlRow| {
-                let mut proj = ProjectBuilder::new();
-                proj.title(row.get("title"))
-                    .description(row.get("description"));
-                if let Some(cover) = row.get::<Option<String>, _>("cover") {
-                    if let Ok(cover_link) = Url::parse(&cover) {
-                        proj.cover(cover_link);
-                    }
+/// How project lists are sorted: featured projects first, then by position.
+pub(crate) const ORDER: &str = "featured DESC, position, id";
+
+pub(crate) fn project_row(row: &MySqlRow) -> (u32, ProjectBuilder) {
+    let mut proj = ProjectBuilder::new();
+    proj.id(row.get("id"))
+        .title(row.get("title"))
+        .description(row.get("description"))
+        .featured(row.get("featured"))
+        .position(row.get("position"));
+    if let Some(cover) = row.get::<Option<String>, _>("cover")
+        && let Ok(cover_link) = Url::parse(&cover)
+    {
+        proj.cover(cover_link);
+    }
+    (row.get::<u32, _>("id"), proj)
+}
+
+/// Loads every project, or only project `id` if given, from either the live set or the trash.
+pub(crate) async fn load(
+    db: &mut MySqlConnection,
+    id: Option<u32>,
+    trashed: bool,
+) -> Result<Vec<Project>> {
+    let projects_incomplete =
+        sqlx::query(&format!("SELECT * FROM projects WHERE (deleted_at IS NOT NULL) = ? AND (? IS NULL OR id = ?) ORDER BY {ORDER}"))
+            .bind(trashed)
+            .bind(id)
+            .bind(id)
+            .map(|row: MySqlRow| project_row(&row))
+            .fetch_all(&mut *db)
+            .await?;
+    assemble(db, projects_incomplete).await
+}
+
+/// Appends `(?, ?, ...)` binding every item, for use after `IN`.
+pub(crate) fn push_list<'q, 'a, T>(
+    query: &'q mut QueryBuilder<'a, MySql>,
+    items: impl IntoIterator<Item = T>,
+) -> &'q mut QueryBuilder<'a, MySql>
+where
+    T: 'a + sqlx::Encode<'a, MySql> + sqlx::Type<MySql> + Send,
+{
+    query.push("(");
+    let mut list = query.separated(", ");
+    for item in items {
+        list.push_bind(item);
+    }
+    list.push_unseparated(")");
+    query
+}
+
+/// Adds the tags and links of each project. Stored data is built with the
+/// [permissive policy](ValidationPolicy::permissive), so only rows that cannot form a project at
+/// all, like a link whose URL does not parse, are skipped.
+pub(crate) async fn assemble(
+    db: &mut MySqlConnection,
+    projects_incomplete: Vec<(u32, ProjectBuilder)>,
+) -> Result<Vec<Project>> {
+    if projects_incomplete.is_empty() {
+        return Ok(vec![]);
+    }
+    let ids = projects_incomplete.iter().map(|(id, _)| *id).collect_vec();
+    let mut tags =
+        QueryBuilder::<MySql>::new("SELECT project_id, tag FROM project_tags WHERE project_id IN ");
+    push_list(&mut tags, ids.iter().copied()).push(" ORDER BY id");
+    let mut tags = tags
+        .build()
+        .map(|row: MySqlRow| (row.get::<u32, _>("project_id"), row.get::<String, _>("tag")))
+        .fetch_all(&mut *db)
+        .await?
+        .into_iter()
+        .into_group_map();
+    let mut links = QueryBuilder::<MySql>::new(
+        "SELECT project_id, name, link, role, position, is_primary FROM project_links \
+         WHERE project_id IN ",
+    );
+    push_list(&mut links, ids.iter().copied()).push(" ORDER BY id");
+    let mut links = links
+        .build()
+        .map(|row: MySqlRow| {
+            let proj_id: u32 = row.get("project_id");
+            let mut link_builder = LinkBuilder::new();
+            link_builder.name(row.get("name"));
+            if let Some(link) = row.get::<Option<String>, _>("link")
+                && let Ok(link) = Url::parse(&link)
+            {
+                link_builder.url(link);
+            }
+            if let Some(role) = row.get::<Option<String>, _>("role").as_deref() {
+                match LinkRole::parse(role) {
+                    Some(role) => _ = link_builder.role(role),
+                    None => log::warn!("ignoring unknown link role {role:?} on project {proj_id}"),
                 }
-                (row.get::<u32, _>("id"), proj)
-            })
-            .fetch_all(&mut **db)
+            }
+            if let Some(order) = row.get::<Option<u32>, _>("position") {
+                link_builder.order(order);
+            }
+            link_builder.primary(row.get("is_primary"));
+            (proj_id, link_builder)
+        })
+        .fetch_all(&mut *db)
+        .await?
+        .into_iter()
+        .into_group_map();
+
+    Ok(projects_incomplete
+        .into_iter()
+        .filter_map(|(id, mut proj)| {
+            for tag in tags.remove(&id).unwrap_or_default() {
+                proj.add_tag(&tag);
+            }
+            for link in links.remove(&id).unwrap_or_default() {
+                match link.bulid_with(ValidationPolicy::permissive()) {
+                    Ok(link) => _ = proj.add_link(link),
+                    Err(e) => log::warn!("skipping invalid link on project {id}: {e}"),
+                }
+            }
+            proj.bulid_with(ValidationPolicy::permissive())
+                .inspect_err(|e| log::warn!("skipping invalid project {id}: {e}"))
+                .ok()
+        })
+        .collect())
+}
+
+impl Projects {
+    pub async fn get(db: &mut MySqlConnection) -> Result<Projects> {
+        Ok(Projects {
+            projects: load(db, None, false).await?,
+        })
+    }
+}
+
+impl Project {
+    pub async fn get(id: u32, db: &mut MySqlConnection) -> Result<Option<Project>> {
+        Ok(load(db, Some(id), false).await?.pop())
+    }
+
+    /// Inserts the project, keeping its id if it has one, and returns the id of the new row.
+    /// Without a position it goes after every other project. `author` is recorded in the revision
+    /// history.
+    pub async fn insert(&self, author: &str, db: &mut MySqlConnection) -> Result<u32> {
+        let mut tx = db.begin().await?;
+        let position = match self.position {
+            Some(position) => position,
+            None => sqlx::query_scalar::<_, u32>(
+                "SELECT CAST(COALESCE(MAX(position), 0) + 1 AS UNSIGNED) FROM projects FOR UPDATE",
+            )
+            .fetch_one(&mut *tx)
+            .await?,
+        };
+        let id = sqlx::query(
+            "INSERT INTO projects (id, title, description, cover, featured, position) \
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(self.id)
+        .bind(&self.title)
+        .bind(&self.description)
+        .bind(self.cover.as_ref().map(Url::as_str))
+        .bind(self.featured)
+        .bind(position)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_id() as u32;
+        self.insert_children(id, &mut tx).await?;
+        Revision::record(id, self, author, &mut tx).await?;
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    /// Replaces the stored project `id` with this one, keeping its position unless this one has
+    /// one. Returns `false` if there is no such project or it is in the trash.
+    pub async fn update(&self, id: u32, author: &str, db: &mut MySqlConnection) -> Result<bool> {
+        let mut tx = db.begin().await?;
+        let exists =
+            sqlx::query("SELECT id FROM projects WHERE id = ? AND deleted_at IS NULL FOR UPDATE")
+                .bind(id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .is_some();
+        if !exists {
+            return Ok(false);
+        }
+        sqlx::query(
+            "UPDATE projects SET title = ?, description = ?, cover = ?, featured = ?, \
+             position = COALESCE(?, position) WHERE id = ?",
+        )
+        .bind(&self.title)
+        .bind(&self.description)
+        .bind(self.cover.as_ref().map(Url::as_str))
+        .bind(self.featured)
+        .bind(self.position)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM project_tags WHERE project_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
             .await?;
-        let tags = sqlx::query("SELECT project_id, tag FROM project_tags")
-            .map(|row: MySqlRow| (row.get::<u32, _>("project_id"), row.get::<String, _>("tag")))
-            .fetch_all(&mut **db)
+        sqlx::query("DELETE FROM project_links WHERE project_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
             .await?;
-        let links = sqlx::query("SELECT project_id, name, link FROM project_tags")
-            .map(|row: MySqlRow| {
-                let proj_id: u32 = row.get("project_id");
-                let mut link_builder = LinkBuilder::new();
-                link_builder.name(row.get("name"));
-                if let Some(link) = row.get::<Option<String>, _>("link") {
-                    if let Ok(link) = Url::parse(&link) {
-                        link_builder.url(link);
-                    }
-                }
-                (proj_id, link_builder)
-            })
-            .fetch_all(&mut **db)
+        self.insert_children(id, &mut tx).await?;
+        Revision::record(id, self, author, &mut tx).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Moves project `id` to the trash. Returns `false` if there is no such live project.
+    pub async fn delete(id: u32, db: &mut MySqlConnection) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE projects SET deleted_at = CURRENT_TIMESTAMP WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn insert_children(&self, id: u32, db: &mut MySqlConnection) -> Result<()> {
+        for tag in &self.tags {
+            sqlx::query("INSERT INTO project_tags (project_id, tag) VALUES (?, ?)")
+                .bind(id)
+                .bind(tag)
+                .execute(&mut *db)
+                .await?;
+        }
+        for link in &self.links {
+            sqlx::query(
+                "INSERT INTO project_links (project_id, name, link, role, position, is_primary) \
+                 VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(id)
+            .bind(&link.name)
+            .bind(link.link.as_str())
+            .bind(link.role.map(LinkRole::as_str))
+            .bind(link.order)
+            .bind(link.primary)
+            .execute(&mut *db)
             .await?;
-        todo!()
+        }
+        Ok(())
     }
 }
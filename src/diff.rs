@@ -0,0 +1,270 @@
+//! Structural differences between two versions of a [`Project`].
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    Link, Project, ProjectBuilderError,
+    builders::{Edit, ProjectBuilder},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change<T> {
+    pub from: T,
+    pub to: T,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkChange {
+    pub name: String,
+    pub from: Link,
+    pub to: Link,
+}
+
+/// Everything that differs between two projects. Tags are compared as a set and links are matched
+/// by name, so reordering either is not a change. Neither is a different position, which belongs
+/// to the ordering of all projects rather than to the project.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectDiff {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<Change<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<Change<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover: Option<Change<Option<Url>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub featured: Option<Change<bool>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags_added: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags_removed: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub links_added: Vec<Link>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub links_removed: Vec<Link>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub links_changed: Vec<LinkChange>,
+}
+
+fn change<T: PartialEq + Clone>(from: &T, to: &T) -> Option<Change<T>> {
+    (from != to).then(|| Change {
+        from: from.clone(),
+        to: to.clone(),
+    })
+}
+
+impl Project {
+    pub fn diff(&self, other: &Project) -> ProjectDiff {
+        let find =
+            |links: &[Link], name: &str| links.iter().find(|link| link.name == name).cloned();
+        ProjectDiff {
+            title: change(&self.title, &other.title),
+            description: change(&self.description, &other.description),
+            cover: change(&self.cover, &other.cover),
+            featured: change(&self.featured, &other.featured),
+            tags_added: other
+                .tags
+                .iter()
+                .filter(|tag| !self.tags.contains(tag))
+                .cloned()
+                .collect(),
+            tags_removed: self
+                .tags
+                .iter()
+                .filter(|tag| !other.tags.contains(tag))
+                .cloned()
+                .collect(),
+            links_added: other
+                .links
+                .iter()
+                .filter(|link| find(&self.links, &link.name).is_none())
+                .cloned()
+                .collect(),
+            links_removed: self
+                .links
+                .iter()
+                .filter(|link| find(&other.links, &link.name).is_none())
+                .cloned()
+                .collect(),
+            links_changed: self
+                .links
+                .iter()
+                .filter_map(|link| {
+                    let new = find(&other.links, &link.name)?;
+                    (new != *link).then(|| LinkChange {
+                        name: link.name.clone(),
+                        from: link.clone(),
+                        to: new,
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ProjectDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == ProjectDiff::default()
+    }
+
+    /// Replays the diff onto `builder` as a patch.
+    pub fn apply<'b>(&self, builder: &'b mut ProjectBuilder) -> &'b mut ProjectBuilder {
+        if let Some(Change { to, .. }) = &self.title {
+            builder.title(to);
+        }
+        if let Some(Change { to, .. }) = &self.description {
+            builder.description(to);
+        }
+        match &self.cover {
+            Some(Change { to: Some(to), .. }) => _ = builder.cover(to.clone()),
+            Some(Change { to: None, .. }) => _ = builder.remove_cover(),
+            None => {}
+        }
+        if let Some(Change { to, .. }) = &self.featured {
+            builder.featured(*to);
+        }
+        for tag in &self.tags_removed {
+            builder.remove_tag(tag);
+        }
+        for tag in &self.tags_added {
+            builder.add_tag(tag);
+        }
+        for link in &self.links_removed {
+            builder.remove_link(&link.name);
+        }
+        for LinkChange { name, to, .. } in &self.links_changed {
+            builder.remove_link(name).add_link(to.clone());
+        }
+        for link in &self.links_added {
+            builder.add_link(link.clone());
+        }
+        builder
+    }
+
+    pub fn apply_to(&self, project: Project) -> Result<Project, ProjectBuilderError> {
+        self.apply(&mut project.edit()).bulid()
+    }
+}
+
+impl fmt::Display for ProjectDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(Change { from, to }) = &self.title {
+            writeln!(f, "title: {from:?} -> {to:?}")?;
+        }
+        if let Some(Change { from, to }) = &self.description {
+            writeln!(f, "description: {from:?} -> {to:?}")?;
+        }
+        if let Some(Change { from, to }) = &self.cover {
+            let show = |url: &Option<Url>| url.as_ref().map_or("none".to_string(), Url::to_string);
+            writeln!(f, "cover: {} -> {}", show(from), show(to))?;
+        }
+        if let Some(Change { from, to }) = &self.featured {
+            writeln!(f, "featured: {from} -> {to}")?;
+        }
+        for tag in &self.tags_added {
+            writeln!(f, "+ tag {tag:?}")?;
+        }
+        for tag in &self.tags_removed {
+            writeln!(f, "- tag {tag:?}")?;
+        }
+        for link in &self.links_added {
+            writeln!(f, "+ link {:?} {link}", link.name)?;
+        }
+        for link in &self.links_removed {
+            writeln!(f, "- link {:?} {link}", link.name)?;
+        }
+        for LinkChange { name, from, to } in &self.links_changed {
+            writeln!(f, "~ link {name:?} {from} -> {to}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builders::LinkBuilder;
+
+    fn link(name: &str, url: &str) -> Link {
+        LinkBuilder::new()
+            .name(name)
+            .url(Url::parse(url).unwrap())
+            .bulid()
+            .unwrap()
+    }
+    fn base() -> Project {
+        ProjectBuilder::new()
+            .title("blogger")
+            .description("portfolio backend")
+            .add_tag("Rust")
+            .add_tag("MySQL")
+            .add_link(link("Source", "https://github.com/a/b"))
+            .add_link(link("Docs", "https://docs.rs/b"))
+            .bulid()
+            .unwrap()
+    }
+
+    #[test]
+    fn identical_projects() {
+        assert!(base().diff(&base()).is_empty());
+    }
+    #[test]
+    fn field_level_diff() {
+        let old = base();
+        let mut builder = old.clone().edit();
+        builder
+            .title("blogger 2")
+            .cover(Url::parse("https://example.com/a.png").unwrap())
+            .featured(true)
+            .position(7)
+            .remove_tag("MySQL")
+            .add_tag("Rocket")
+            .remove_link("Docs")
+            .remove_link("Source")
+            .add_link(link("Source", "https://codeberg.org/a/b"))
+            .add_link(link("Demo", "https://example.com"));
+        let new = builder.bulid().unwrap();
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.title,
+            Some(Change {
+                from: "blogger".to_string(),
+                to: "blogger 2".to_string()
+            })
+        );
+        assert_eq!(diff.description, None);
+        assert_eq!(
+            diff.featured,
+            Some(Change {
+                from: false,
+                to: true
+            })
+        );
+        assert_eq!(diff.tags_added, vec!["Rocket"]);
+        assert_eq!(diff.tags_removed, vec!["MySQL"]);
+        assert_eq!(diff.links_added, vec![link("Demo", "https://example.com")]);
+        assert_eq!(diff.links_removed[0].name, "Docs");
+        assert_eq!(diff.links_changed[0].name, "Source");
+        assert!(diff.apply_to(old).unwrap().diff(&new).is_empty());
+    }
+    #[test]
+    fn json_round_trip() {
+        let mut builder = base().edit();
+        builder.remove_tag("Rust").description("changed");
+        let diff = base().diff(&builder.bulid().unwrap());
+
+        let json = serde_json::to_value(&diff).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "description": { "from": "portfolio backend", "to": "changed" },
+                "tags_removed": ["Rust"],
+            })
+        );
+        let decoded: ProjectDiff = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, diff);
+    }
+}
@@ -1,5 +1,10 @@
+use std::path::PathBuf;
+
+use rocket_db_pools::sqlx;
 use thiserror::Error;
 
+use crate::Project;
+
 #[derive(Error, Debug, PartialEq, Eq)]
 pub enum ProjectBuilderError {
     #[error("Missing Title")]
@@ -16,6 +21,22 @@ pub enum ProjectBuilderError {
     DuplicateTags = 32,
     #[error("Duplicate Links")]
     DuplicateLinks = 64,
+    #[error("Title Length Out Of Range")]
+    TitleLength = 128,
+    #[error("Description Length Out Of Range")]
+    DescriptionLength = 256,
+    #[error("Tag Length Out Of Range")]
+    TagLength = 512,
+    #[error("Too Many Tags")]
+    TooManyTags = 1024,
+    #[error("Too Many Links")]
+    TooManyLinks = 2048,
+    #[error("Tag Not Allowed")]
+    UnknownTag = 4096,
+    #[error("More Than One Primary Link")]
+    PrimaryLinks = 8192,
+    #[error("Duplicate Link Names")]
+    DuplicateLinkNames = 16384,
 }
 #[derive(Error, Debug, PartialEq, Eq)]
 pub enum LinkBuilderError {
@@ -23,4 +44,211 @@ pub enum LinkBuilderError {
     Name = 1,
     #[error("Missing URL")]
     Url = 2,
+    #[error("Link name Length Out Of Range")]
+    NameLength = 4,
+    #[error("URL Scheme Not Allowed")]
+    Scheme = 8,
+    #[error("URL Host Not Allowed")]
+    Host = 16,
+}
+
+impl ProjectBuilderError {
+    /// JSON pointer to the offending field.
+    pub fn pointer(&self) -> &'static str {
+        match self {
+            ProjectBuilderError::Title | ProjectBuilderError::TitleLength => "/title",
+            ProjectBuilderError::Description | ProjectBuilderError::DescriptionLength => {
+                "/description"
+            }
+            ProjectBuilderError::Cover => "/cover",
+            ProjectBuilderError::Tags
+            | ProjectBuilderError::DuplicateTags
+            | ProjectBuilderError::TagLength
+            | ProjectBuilderError::TooManyTags
+            | ProjectBuilderError::UnknownTag => "/tags",
+            ProjectBuilderError::Links
+            | ProjectBuilderError::DuplicateLinks
+            | ProjectBuilderError::TooManyLinks
+            | ProjectBuilderError::PrimaryLinks
+            | ProjectBuilderError::DuplicateLinkNames => "/links",
+        }
+    }
+}
+impl LinkBuilderError {
+    /// JSON pointer to the offending field, relative to the link.
+    pub fn pointer(&self) -> &'static str {
+        match self {
+            LinkBuilderError::Name | LinkBuilderError::NameLength => "/name",
+            LinkBuilderError::Url | LinkBuilderError::Scheme | LinkBuilderError::Host => "/link",
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum PatchError {
+    #[error("Patch must be a JSON object or array")]
+    Document,
+    #[error("Unsupported operation {0:?}")]
+    Operation(String),
+    #[error("Cannot patch {0}")]
+    Path(String),
+    #[error("Invalid value for {0}")]
+    Value(String),
+    #[error("Test failed at {0}")]
+    Test(String),
+    /// A problem with the link at this index of `/links`.
+    #[error("{1}")]
+    Link(usize, #[source] LinkBuilderError),
+    #[error(transparent)]
+    Project(#[from] ProjectBuilderError),
+}
+impl PatchError {
+    pub fn pointer(&self) -> String {
+        match self {
+            PatchError::Document | PatchError::Operation(_) => String::new(),
+            PatchError::Path(path) | PatchError::Value(path) | PatchError::Test(path) => {
+                path.clone()
+            }
+            PatchError::Link(index, e) => format!("/links/{index}{}", e.pointer()),
+            PatchError::Project(e) => e.pointer().to_string(),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum TagError {
+    #[error("Tag {0:?} not found")]
+    NotFound(String),
+    #[error("Tag {0:?} already exists")]
+    Exists(String),
+    #[error("Projects {0:?} would be left without tags")]
+    LastTag(Vec<u32>),
+    #[error("Tag {0:?} cannot be its own ancestor")]
+    Cycle(String),
+    #[error("Tag name {0:?} has an invalid length")]
+    Name(String),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum RewriteError {
+    #[error("No rewrite rules given")]
+    NoRules,
+    #[error("Invalid rewrite rule: {0}")]
+    Rule(String),
+    #[error("Rewriting {from:?} gives the invalid URL {to:?}")]
+    InvalidUrl { from: String, to: String },
+    #[error("Rewritten URL {0:?} is not allowed")]
+    NotAllowed(String),
+    #[error("Project #{0} would be invalid after the rewrite")]
+    Invalid(u32),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum OrderError {
+    #[error("Project {0} not found")]
+    NotFound(u32),
+    #[error("Project {0} is listed more than once")]
+    Duplicate(u32),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// A query that cannot be parsed. Positions count characters from the start of the query.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum QueryError {
+    #[error("Unexpected {found} at position {position}")]
+    Unexpected { found: String, position: usize },
+    #[error("Unknown field {field:?} at position {position}")]
+    UnknownField { field: String, position: usize },
+    #[error("Unterminated string starting at position {0}")]
+    UnterminatedString(usize),
+    #[error("Unexpected end of query")]
+    End,
+    #[error("Query nests too deeply at position {0}")]
+    TooDeep(usize),
+}
+
+#[derive(Error, Debug)]
+pub enum ApiError {
+    #[error("{0} not found")]
+    NotFound(&'static str),
+    #[error(transparent)]
+    Project(#[from] ProjectBuilderError),
+    #[error("Invalid patch")]
+    Patch(Vec<PatchError>),
+    #[error("If-Match header is required")]
+    PreconditionRequired,
+    /// The project changed since the client last saw it; carries the current version.
+    #[error("Project was modified")]
+    PreconditionFailed(Box<Project>),
+    #[error(transparent)]
+    Tag(#[from] TagError),
+    #[error(transparent)]
+    Query(#[from] QueryError),
+    /// The `after` cursor names a project that no longer exists.
+    #[error("Cursor {0} does not point at a project")]
+    Cursor(u32),
+    #[error(transparent)]
+    Order(#[from] OrderError),
+    #[error(transparent)]
+    Rewrite(#[from] RewriteError),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum ContentError {
+    #[error("Unsupported content file")]
+    Unsupported,
+    #[error("Missing +++ front matter")]
+    FrontMatter,
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid front matter: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("Cannot write front matter: {0}")]
+    TomlSer(#[from] toml::ser::Error),
+    #[error(transparent)]
+    Project(#[from] ProjectBuilderError),
+    #[error("{}: {source}", path.display())]
+    InFile {
+        path: PathBuf,
+        source: Box<ContentError>,
+    },
+}
+#[derive(Error, Debug)]
+pub enum SyncError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error(transparent)]
+    Content(#[from] ContentError),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Project(#[from] ProjectBuilderError),
+}
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error("Usage: {0}")]
+    Usage(&'static str),
+    #[error("Configuration error: {0}")]
+    Config(#[from] Box<rocket::figment::Error>),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error(transparent)]
+    Content(#[from] ContentError),
+    #[error(transparent)]
+    Sync(#[from] SyncError),
+    #[error(transparent)]
+    Tag(#[from] TagError),
+    #[error(transparent)]
+    Rewrite(#[from] RewriteError),
+    #[error("HTTP client error: {0}")]
+    Http(#[from] reqwest::Error),
 }
@@ -0,0 +1,184 @@
+//! Entity tags for optimistic concurrency control.
+//!
+//! A project's ETag is derived from its JSON representation, so any change to it, whichever way it
+//! was made, yields a new tag.
+use std::convert::Infallible;
+
+use rocket::{
+    Request, Responder,
+    http::Header,
+    request::{FromRequest, Outcome},
+    serde::json::Json,
+};
+use rocket_db_pools::sqlx::{self, Connection, MySqlConnection, Result};
+
+use crate::Project;
+
+/// 64-bit FNV-1a, chosen because it is stable across builds and platforms.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl Project {
+    pub fn etag(&self) -> String {
+        let json = serde_json::to_vec(self).expect("projects always serialize");
+        format!("\"{:016x}\"", fnv1a(&json))
+    }
+}
+
+/// The `If-Match` header of a request, if it has one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IfMatch(pub Option<String>);
+
+impl IfMatch {
+    /// Whether `etag` satisfies the header. Uses the strong comparison `If-Match` requires, so weak
+    /// (`W/`) tags never match.
+    pub fn matches(&self, etag: &str) -> bool {
+        let Some(header) = &self.0 else {
+            return false;
+        };
+        header
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || tag == etag)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfMatch {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(IfMatch(
+            request.headers().get_one("If-Match").map(str::to_string),
+        ))
+    }
+}
+
+/// A project sent along with its `ETag` header.
+#[derive(Responder)]
+pub struct Tagged {
+    inner: Json<Project>,
+    etag: Header<'static>,
+}
+
+impl From<Project> for Tagged {
+    fn from(project: Project) -> Self {
+        Tagged {
+            etag: Header::new("ETag", project.etag()),
+            inner: Json(project),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Conditional {
+    Updated,
+    NotFound,
+    /// The project no longer matches `If-Match`; this is its current version.
+    Changed(Box<Project>),
+}
+
+impl Project {
+    /// Like [`Project::update`], but only if the stored version still satisfies `if_match`.
+    pub async fn update_if_match(
+        &self,
+        id: u32,
+        if_match: &IfMatch,
+        author: &str,
+        db: &mut MySqlConnection,
+    ) -> Result<Conditional> {
+        let mut tx = db.begin().await?;
+        sqlx::query("SELECT id FROM projects WHERE id = ? FOR UPDATE")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        let Some(current) = Project::get(id, &mut tx).await? else {
+            return Ok(Conditional::NotFound);
+        };
+        if !if_match.matches(&current.etag()) {
+            return Ok(Conditional::Changed(Box::new(current)));
+        }
+        self.update(id, author, &mut tx).await?;
+        tx.commit().await?;
+        Ok(Conditional::Updated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ProjectBuilderError,
+        builders::{Edit, ProjectBuilder},
+        tags::{Tag, TagRegistry},
+        validation::ValidationPolicy,
+    };
+
+    #[test]
+    fn etag_follows_content() {
+        let project = ProjectBuilder::sample(&["Rust"]).id(1).bulid().unwrap();
+        let etag = project.etag();
+        assert_eq!(
+            etag,
+            ProjectBuilder::sample(&["Rust"])
+                .id(1)
+                .bulid()
+                .unwrap()
+                .etag()
+        );
+        assert!(etag.starts_with('"') && etag.ends_with('"'));
+
+        let mut builder = project.edit();
+        builder.add_tag("Rocket");
+        assert_ne!(builder.bulid().unwrap().etag(), etag);
+    }
+    #[test]
+    fn if_match() {
+        let etag = ProjectBuilder::sample(&["Rust"]).bulid().unwrap().etag();
+        assert!(!IfMatch(None).matches(&etag));
+        assert!(IfMatch(Some("*".to_string())).matches(&etag));
+        assert!(IfMatch(Some(etag.clone())).matches(&etag));
+        assert!(IfMatch(Some(format!("\"stale\", {etag}"))).matches(&etag));
+        assert!(!IfMatch(Some(format!("W/{etag}"))).matches(&etag));
+        assert!(!IfMatch(Some("\"stale\"".to_string())).matches(&etag));
+    }
+    #[test]
+    fn etag_survives_aliases() {
+        let registry = TagRegistry::new([Tag {
+            name: "Etag Test".to_string(),
+            color: None,
+            icon: None,
+            description: None,
+            aliases: vec!["etag-alias".to_string()],
+            parent: None,
+        }]);
+        let body = |tags: &[&str]| {
+            serde_json::from_value::<ProjectBuilder>(serde_json::json!({
+                "id": 1,
+                "title": "Project",
+                "description": "Description",
+                "tags": tags,
+                "links": [{ "name": "Example", "link": "https://example.com" }],
+            }))
+            .unwrap()
+            .bulid_with_registry(ValidationPolicy::global(), &registry)
+        };
+        // PUT stores the canonical name, which is what the next GET loads.
+        let put = body(&["etag-alias"]).unwrap();
+        assert_eq!(put.tags, ["Etag Test"]);
+        let tags = put.tags.iter().map(String::as_str).collect::<Vec<_>>();
+        let get = ProjectBuilder::sample(&tags)
+            .id(1)
+            .bulid_with_registry(ValidationPolicy::permissive(), &registry)
+            .unwrap();
+        assert!(IfMatch(Some(put.etag())).matches(&get.etag()));
+
+        assert_eq!(
+            body(&["etag-alias", "Etag Test"]),
+            Err(ProjectBuilderError::DuplicateTags)
+        );
+    }
+}
@@ -0,0 +1,255 @@
+//! Filtering and pagination of the project list, done in SQL.
+use rocket::FromForm;
+use rocket_db_pools::sqlx::{
+    self, MySql, MySqlConnection, QueryBuilder, Result, Row, mysql::MySqlRow,
+};
+use serde::Serialize;
+
+use crate::{
+    Project, Projects,
+    db::{ORDER, assemble, project_row, push_list},
+    query::Query,
+    tags::TagRegistry,
+};
+
+pub const DEFAULT_LIMIT: u32 = 20;
+pub const MAX_LIMIT: u32 = 100;
+
+/// Query parameters of `GET /api/projects`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, FromForm)]
+pub struct ProjectFilter {
+    /// Projects must have any of these tags, or a tag under one of them.
+    pub tag: Vec<String>,
+    /// Require every tag in `tag` instead of any.
+    pub all: bool,
+    /// Text to look for in the title or description.
+    pub q: Option<String>,
+    /// A boolean expression in the [`query`](crate::query) language. Parsed by the caller and
+    /// passed to [`Projects::find`].
+    pub query: Option<String>,
+    /// Only projects after this cursor, as returned in [`Page::next`].
+    pub after: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Page {
+    pub projects: Vec<Project>,
+    /// Number of projects matching the filter, across every page.
+    pub total: i64,
+    /// Cursor of the next page, if there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<u32>,
+}
+
+/// Escapes `%`, `_` and `\` so `text` matches literally inside a `LIKE` pattern.
+pub(crate) fn like(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+impl ProjectFilter {
+    pub fn limit(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+
+    /// Appends the conditions of the filter, except the cursor, to a query on `projects`.
+    pub(crate) fn push_conditions(
+        &self,
+        query: &mut QueryBuilder<'_, MySql>,
+        expression: Option<&Query>,
+        registry: &TagRegistry,
+    ) {
+        query.push(" WHERE deleted_at IS NULL");
+        let groups = self
+            .tag
+            .iter()
+            .map(|tag| registry.descendants(tag))
+            .collect::<Vec<_>>();
+        let groups = if self.all {
+            groups
+        } else {
+            vec![groups.into_iter().flatten().collect()]
+        };
+        for group in groups.into_iter().filter(|group| !group.is_empty()) {
+            query.push(
+                " AND EXISTS (SELECT 1 FROM project_tags WHERE project_tags.project_id = projects.id \
+                 AND LOWER(TRIM(project_tags.tag)) IN ",
+            );
+            let mut group = group.into_iter().collect::<Vec<_>>();
+            group.sort();
+            push_list(query, group).push(")");
+        }
+        if let Some(q) = self.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+            query
+                .push(" AND (title LIKE ")
+                .push_bind(like(q))
+                .push(" OR description LIKE ")
+                .push_bind(like(q))
+                .push(")");
+        }
+        if let Some(expression) = expression {
+            query.push(" AND ");
+            expression.push_sql(query, registry);
+        }
+    }
+
+    fn page_query(
+        &self,
+        expression: Option<&Query>,
+        registry: &TagRegistry,
+    ) -> QueryBuilder<'static, MySql> {
+        let mut query = QueryBuilder::new("SELECT * FROM projects");
+        self.push_conditions(&mut query, expression, registry);
+        if let Some(after) = self.after {
+            // Same as `ORDER`, as a single ascending key.
+            query
+                .push(
+                    " AND (NOT featured, position, id) > \
+                     (SELECT NOT featured, position, id FROM projects WHERE id = ",
+                )
+                .push_bind(after)
+                .push(")");
+        }
+        // One extra row tells whether there is another page.
+        query
+            .push(format!(" ORDER BY {ORDER} LIMIT "))
+            .push_bind(self.limit() + 1);
+        query
+    }
+    fn count_query(
+        &self,
+        expression: Option<&Query>,
+        registry: &TagRegistry,
+    ) -> QueryBuilder<'static, MySql> {
+        let mut query = QueryBuilder::new("SELECT COUNT(*) AS total FROM projects");
+        self.push_conditions(&mut query, expression, registry);
+        query
+    }
+}
+
+impl Projects {
+    /// One page of the live projects matching `filter` and `expression`, featured projects first
+    /// and then by position. `None` if the cursor names a project that was purged, since the
+    /// page after it cannot be found any more.
+    pub async fn find(
+        filter: &ProjectFilter,
+        expression: Option<&Query>,
+        db: &mut MySqlConnection,
+    ) -> Result<Option<Page>> {
+        if let Some(after) = filter.after {
+            let exists = sqlx::query("SELECT 1 FROM projects WHERE id = ?")
+                .bind(after)
+                .fetch_optional(&mut *db)
+                .await?
+                .is_some();
+            if !exists {
+                return Ok(None);
+            }
+        }
+        let registry = TagRegistry::global();
+        let mut rows = filter
+            .page_query(expression, &registry)
+            .build()
+            .map(|row: MySqlRow| project_row(&row))
+            .fetch_all(&mut *db)
+            .await?;
+        let next = if rows.len() > filter.limit() as usize {
+            rows.truncate(filter.limit() as usize);
+            rows.last().map(|(id, _)| *id)
+        } else {
+            None
+        };
+        let total = filter
+            .count_query(expression, &registry)
+            .build()
+            .map(|row: MySqlRow| row.get("total"))
+            .fetch_one(&mut *db)
+            .await?;
+        Ok(Some(Page {
+            projects: assemble(db, rows).await?,
+            total,
+            next,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::Tag;
+
+    fn registry() -> TagRegistry {
+        TagRegistry::new([
+            Tag {
+                name: "Languages".to_string(),
+                color: None,
+                icon: None,
+                description: None,
+                aliases: vec![],
+                parent: None,
+            },
+            Tag {
+                name: "Rust".to_string(),
+                color: None,
+                icon: None,
+                description: None,
+                aliases: vec![],
+                parent: Some("Languages".to_string()),
+            },
+        ])
+    }
+
+    #[test]
+    fn filter_sql() {
+        let filter = ProjectFilter {
+            tag: vec!["Languages".to_string(), "Web".to_string()],
+            q: Some(" blog ".to_string()),
+            after: Some(10),
+            limit: Some(500),
+            ..Default::default()
+        };
+        assert_eq!(
+            filter.page_query(None, &registry()).sql(),
+            "SELECT * FROM projects WHERE deleted_at IS NULL \
+             AND EXISTS (SELECT 1 FROM project_tags WHERE project_tags.project_id = projects.id \
+             AND LOWER(TRIM(project_tags.tag)) IN (?, ?, ?)) \
+             AND (title LIKE ? OR description LIKE ?) \
+             AND (NOT featured, position, id) > \
+             (SELECT NOT featured, position, id FROM projects WHERE id = ?) \
+             ORDER BY featured DESC, position, id LIMIT ?"
+        );
+        assert_eq!(filter.limit(), MAX_LIMIT);
+
+        let all = ProjectFilter {
+            all: true,
+            ..filter
+        };
+        let sql = all.count_query(None, &registry()).into_sql();
+        assert!(sql.starts_with("SELECT COUNT(*) AS total FROM projects WHERE deleted_at IS NULL"));
+        assert_eq!(sql.matches("EXISTS").count(), 2);
+        assert!(!sql.contains("ORDER BY"));
+    }
+    #[test]
+    fn filter_expression_sql() {
+        let expression = Query::parse("tag:rust OR NOT link:docs").unwrap();
+        let sql = ProjectFilter::default()
+            .count_query(Some(&expression), &registry())
+            .into_sql();
+        assert_eq!(
+            sql,
+            "SELECT COUNT(*) AS total FROM projects WHERE deleted_at IS NULL \
+             AND (EXISTS (SELECT 1 FROM project_tags WHERE project_tags.project_id = projects.id \
+             AND LOWER(TRIM(project_tags.tag)) IN (?)) \
+             OR NOT EXISTS (SELECT 1 FROM project_links WHERE project_links.project_id = projects.id \
+             AND project_links.name LIKE ?))"
+        );
+    }
+    #[test]
+    fn like_escapes() {
+        assert_eq!(like("100%_done\\"), "%100\\%\\_done\\\\%");
+    }
+}
@@ -0,0 +1,171 @@
+//! Which tags appear together across projects, for drawing a skills graph.
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use rocket::http::ContentType;
+use serde::Serialize;
+
+use crate::{Project, tags::TagRegistry};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    /// Number of projects with the tag.
+    pub count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TagEdge {
+    pub source: String,
+    pub target: String,
+    /// Number of projects with both tags.
+    pub weight: usize,
+}
+
+/// Tags as nodes, joined by an edge whenever two of them are on the same project. Nodes come most
+/// used first and edges heaviest first.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TagGraph {
+    pub nodes: Vec<TagCount>,
+    pub edges: Vec<TagEdge>,
+}
+
+/// Quotes `id` for DOT.
+fn quote(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl TagGraph {
+    /// Builds the graph of `projects`, colouring tags as in `registry`.
+    pub fn new<'a>(
+        projects: impl IntoIterator<Item = &'a Project>,
+        registry: &TagRegistry,
+    ) -> Self {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut pairs: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+        for project in projects {
+            let tags = project
+                .tags
+                .iter()
+                .map(String::as_str)
+                .collect::<BTreeSet<_>>();
+            for (i, &tag) in tags.iter().enumerate() {
+                *counts.entry(tag).or_default() += 1;
+                for &other in tags.iter().skip(i + 1) {
+                    *pairs.entry((tag, other)).or_default() += 1;
+                }
+            }
+        }
+        let mut nodes = counts
+            .into_iter()
+            .map(|(tag, count)| TagCount {
+                tag: tag.to_string(),
+                count,
+                color: registry.get(tag).and_then(|tag| tag.color.clone()),
+            })
+            .collect::<Vec<_>>();
+        nodes.sort_by_key(|node| Reverse(node.count));
+        let mut edges = pairs
+            .into_iter()
+            .map(|((source, target), weight)| TagEdge {
+                source: source.to_string(),
+                target: target.to_string(),
+                weight,
+            })
+            .collect::<Vec<_>>();
+        edges.sort_by_key(|edge| Reverse(edge.weight));
+        TagGraph { nodes, edges }
+    }
+
+    /// The graph in Graphviz DOT, with nodes labelled by their count and edges as thick as their
+    /// weight.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph tags {\n");
+        for node in &self.nodes {
+            let label = quote(&format!("{} ({})", node.tag, node.count));
+            _ = write!(dot, "    {} [label={label}", quote(&node.tag));
+            if let Some(color) = &node.color {
+                _ = write!(dot, ", color={}", quote(color));
+            }
+            dot.push_str("];\n");
+        }
+        for edge in &self.edges {
+            _ = writeln!(
+                dot,
+                "    {} -- {} [weight={weight}, penwidth={weight}];",
+                quote(&edge.source),
+                quote(&edge.target),
+                weight = edge.weight
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn dot_content_type() -> ContentType {
+        ContentType::new("text", "vnd.graphviz")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{builders::ProjectBuilder, tags::Tag};
+
+    fn graph() -> TagGraph {
+        let registry = TagRegistry::new([Tag {
+            name: "Rust".to_string(),
+            color: Some("#dea584".to_string()),
+            icon: None,
+            description: None,
+            aliases: vec![],
+            parent: None,
+        }]);
+        let projects = [
+            &["Rust", "WASM"][..],
+            &["Rust", "WASM", "Web"],
+            &["Rust"],
+            &["Say \"hi\""],
+        ]
+        .map(|tags| ProjectBuilder::sample(tags).bulid().unwrap());
+        TagGraph::new(&projects, &registry)
+    }
+
+    #[test]
+    fn cooccurrence() {
+        let graph = graph();
+        let nodes = graph
+            .nodes
+            .iter()
+            .map(|node| (node.tag.as_str(), node.count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            nodes,
+            [("Rust", 3), ("WASM", 2), ("Say \"hi\"", 1), ("Web", 1)]
+        );
+        assert_eq!(graph.nodes[0].color.as_deref(), Some("#dea584"));
+        let edges = graph
+            .edges
+            .iter()
+            .map(|edge| (edge.source.as_str(), edge.target.as_str(), edge.weight))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            edges,
+            [("Rust", "WASM", 2), ("Rust", "Web", 1), ("WASM", "Web", 1)]
+        );
+    }
+    #[test]
+    fn dot() {
+        let dot = graph().to_dot();
+        assert!(dot.starts_with("graph tags {\n"));
+        assert!(dot.contains("    \"Rust\" [label=\"Rust (3)\", color=\"#dea584\"];\n"));
+        assert!(dot.contains("    \"Say \\\"hi\\\"\" [label=\"Say \\\"hi\\\" (1)\"];\n"));
+        assert!(dot.contains("    \"Rust\" -- \"WASM\" [weight=2, penwidth=2];\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+}
@@ -0,0 +1,557 @@
+//! Periodic checks that link and cover URLs still work.
+//!
+//! Every http(s) link and cover of a live project is requested with `HEAD`, falling back to `GET`
+//! for servers that reject `HEAD`. Redirects are followed by hand so permanent ones can be
+//! reported, and the outcome of each URL is kept in `link_checks`.
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::{Client, Method, StatusCode, header::LOCATION, redirect};
+use rocket::{
+    Orbit, Rocket,
+    fairing::{Fairing, Info, Kind},
+    figment::Figment,
+    tokio::{self, sync::Semaphore, task::JoinSet, time::Instant},
+};
+use rocket_db_pools::{
+    Database,
+    sqlx::{self, Connection, MySqlConnection, MySqlPool, Result, Row, mysql::MySqlRow},
+};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{Project, Projects, db::BloggerDatabase};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkCheck {
+    pub url: Url,
+    /// Status of the last response, `None` if no response arrived.
+    pub status: Option<u16>,
+    /// Where the redirects ended up, if there were any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub final_url: Option<Url>,
+    /// Every redirect was a `301` or `308`.
+    pub permanent_redirect: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Unix timestamp in seconds.
+    pub checked_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkProblem {
+    /// The request failed or ended in an error status.
+    Broken,
+    /// The URL permanently redirects somewhere else.
+    Moved,
+}
+
+impl LinkCheck {
+    fn new(url: &Url) -> LinkCheck {
+        LinkCheck {
+            url: url.clone(),
+            status: None,
+            final_url: None,
+            permanent_redirect: false,
+            error: None,
+            checked_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |now| now.as_secs() as i64),
+        }
+    }
+
+    pub fn problem(&self) -> Option<LinkProblem> {
+        let broken = self.error.is_some()
+            || self
+                .status
+                .is_none_or(|status| StatusCode::from_u16(status).is_ok_and(|s| !s.is_success()));
+        if broken {
+            Some(LinkProblem::Broken)
+        } else if self.permanent_redirect {
+            Some(LinkProblem::Moved)
+        } else {
+            None
+        }
+    }
+
+    /// Every stored check.
+    pub async fn load(db: &mut MySqlConnection) -> Result<Vec<LinkCheck>> {
+        sqlx::query(
+            "SELECT url, status, final_url, permanent_redirect, error, \
+             CAST(UNIX_TIMESTAMP(checked_at) AS SIGNED) AS checked_at FROM link_checks",
+        )
+        .try_map(|row: MySqlRow| {
+            let url = |column: &str| -> Result<Option<Url>> {
+                row.get::<Option<String>, _>(column)
+                    .map(|url| Url::parse(&url))
+                    .transpose()
+                    .map_err(|e| sqlx::Error::Decode(Box::new(e)))
+            };
+            Ok(LinkCheck {
+                url: url("url")?.unwrap_or_else(|| unreachable!("url is NOT NULL")),
+                status: row.get("status"),
+                final_url: url("final_url")?,
+                permanent_redirect: row.get("permanent_redirect"),
+                error: row.get("error"),
+                checked_at: row.get("checked_at"),
+            })
+        })
+        .fetch_all(db)
+        .await
+    }
+
+    /// Stores `checks`, replacing earlier checks of the same URLs.
+    pub async fn save(checks: &[LinkCheck], db: &mut MySqlConnection) -> Result<()> {
+        let mut tx = db.begin().await?;
+        for check in checks {
+            sqlx::query(
+                "INSERT INTO link_checks \
+                 (url, status, final_url, permanent_redirect, error, checked_at) \
+                 VALUES (?, ?, ?, ?, ?, FROM_UNIXTIME(?)) \
+                 ON DUPLICATE KEY UPDATE status = VALUES(status), \
+                 final_url = VALUES(final_url), permanent_redirect = VALUES(permanent_redirect), \
+                 error = VALUES(error), checked_at = VALUES(checked_at)",
+            )
+            .bind(check.url.as_str())
+            .bind(check.status)
+            .bind(check.final_url.as_ref().map(Url::as_str))
+            .bind(check.permanent_redirect)
+            .bind(&check.error)
+            .bind(check.checked_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await
+    }
+}
+
+/// The `[health]` section of `Rocket.toml`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    /// Hours between checks. `0` turns the scheduled check off.
+    pub interval_hours: u64,
+    /// How many URLs are checked at the same time.
+    pub concurrency: usize,
+    /// How long a single request may take.
+    pub timeout_ms: u64,
+    pub max_redirects: usize,
+}
+impl Default for HealthConfig {
+    fn default() -> Self {
+        HealthConfig {
+            interval_hours: 24,
+            concurrency: 8,
+            timeout_ms: 10_000,
+            max_redirects: 10,
+        }
+    }
+}
+impl HealthConfig {
+    /// Reads `[health]`, falling back to the defaults if the section is missing.
+    pub fn from_figment(figment: &Figment) -> Result<Self, Box<rocket::figment::Error>> {
+        if figment.contains("health") {
+            figment.extract_inner("health").map_err(Box::new)
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Checker {
+    client: Client,
+    concurrency: usize,
+    max_redirects: usize,
+}
+
+impl Checker {
+    pub fn new(config: &HealthConfig) -> reqwest::Result<Checker> {
+        let client = Client::builder()
+            .user_agent(concat!("blogger/", env!("CARGO_PKG_VERSION")))
+            .redirect(redirect::Policy::none())
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()?;
+        Ok(Checker {
+            client,
+            concurrency: config.concurrency.max(1),
+            max_redirects: config.max_redirects,
+        })
+    }
+
+    /// Requests `url`, retrying with `GET` if `HEAD` is answered with an error.
+    async fn request(&self, url: &Url) -> reqwest::Result<reqwest::Response> {
+        let response = self.client.request(Method::HEAD, url.clone()).send().await;
+        match response {
+            Ok(response)
+                if response.status().is_client_error() || response.status().is_server_error() =>
+            {
+                self.client.request(Method::GET, url.clone()).send().await
+            }
+            response => response,
+        }
+    }
+
+    pub async fn check(&self, url: &Url) -> LinkCheck {
+        let mut check = LinkCheck::new(url);
+        let mut current = url.clone();
+        let mut redirects = 0;
+        let mut permanent = true;
+        loop {
+            let response = match self.request(&current).await {
+                Ok(response) => response,
+                Err(e) if e.is_timeout() => {
+                    check.error = Some("Timed Out".to_string());
+                    return check;
+                }
+                Err(e) => {
+                    check.error = Some(e.to_string());
+                    return check;
+                }
+            };
+            let status = response.status();
+            check.status = Some(status.as_u16());
+            if !status.is_redirection() {
+                break;
+            }
+            let Some(next) = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| current.join(location).ok())
+            else {
+                check.error = Some("Redirect Without Location".to_string());
+                return check;
+            };
+            if redirects == self.max_redirects {
+                check.error = Some("Too Many Redirects".to_string());
+                return check;
+            }
+            redirects += 1;
+            permanent &= matches!(
+                status,
+                StatusCode::MOVED_PERMANENTLY | StatusCode::PERMANENT_REDIRECT
+            );
+            current = next;
+        }
+        if redirects > 0 {
+            check.permanent_redirect = permanent;
+            check.final_url = Some(current);
+        }
+        check
+    }
+
+    /// Checks every URL, at most `concurrency` at a time, and returns the checks in the same order.
+    pub async fn check_all(&self, urls: Vec<Url>) -> Vec<LinkCheck> {
+        let permits = Arc::new(Semaphore::new(self.concurrency));
+        let mut tasks = JoinSet::new();
+        for (i, url) in urls.into_iter().enumerate() {
+            let checker = self.clone();
+            let permits = permits.clone();
+            tasks.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                (i, checker.check(&url).await)
+            });
+        }
+        let mut checks = tasks.join_all().await;
+        checks.sort_by_key(|(i, _)| *i);
+        checks.into_iter().map(|(_, check)| check).collect()
+    }
+}
+
+/// Every distinct http(s) link and cover URL of `projects`.
+pub fn urls(projects: &[Project]) -> Vec<Url> {
+    projects
+        .iter()
+        .flat_map(|project| {
+            project
+                .cover
+                .iter()
+                .chain(project.links.iter().map(|link| &link.link))
+        })
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Checks the links and covers of every live project and stores the results. No connection is
+/// held while the requests run, since they can take minutes.
+pub async fn check_links(checker: &Checker, pool: &MySqlPool) -> Result<Vec<LinkCheck>> {
+    let projects = Projects::get(&mut *pool.acquire().await?).await?;
+    let checks = checker.check_all(urls(&projects.projects)).await;
+    LinkCheck::save(&checks, &mut *pool.acquire().await?).await?;
+    Ok(checks)
+}
+
+/// A link or cover whose last check found a problem.
+#[derive(Debug, Clone, Serialize)]
+pub struct UnhealthyLink {
+    pub project: u32,
+    pub title: String,
+    /// The link's name, or `None` for the cover.
+    pub link: Option<String>,
+    pub problem: LinkProblem,
+    #[serde(flatten)]
+    pub check: LinkCheck,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    /// Broken links first, then moved ones.
+    pub links: Vec<UnhealthyLink>,
+}
+
+impl HealthReport {
+    pub fn new(projects: &[Project], checks: Vec<LinkCheck>) -> HealthReport {
+        let checks: HashMap<Url, LinkCheck> = checks
+            .into_iter()
+            .map(|check| (check.url.clone(), check))
+            .collect();
+        let mut links = Vec::new();
+        for project in projects {
+            let urls = project.cover.iter().map(|cover| (None, cover)).chain(
+                project
+                    .links
+                    .iter()
+                    .map(|link| (Some(link.name.clone()), &link.link)),
+            );
+            for (name, url) in urls {
+                let Some(check) = checks.get(url) else {
+                    continue;
+                };
+                if let Some(problem) = check.problem() {
+                    links.push(UnhealthyLink {
+                        project: project.id.unwrap_or_default(),
+                        title: project.title.clone(),
+                        link: name,
+                        problem,
+                        check: check.clone(),
+                    });
+                }
+            }
+        }
+        links.sort_by_key(|link| link.problem == LinkProblem::Moved);
+        HealthReport { links }
+    }
+
+    /// Reports on the live projects using the stored checks.
+    pub async fn get(db: &mut MySqlConnection) -> Result<HealthReport> {
+        let projects = Projects::get(db).await?;
+        let checks = LinkCheck::load(db).await?;
+        Ok(HealthReport::new(&projects.projects, checks))
+    }
+}
+
+/// Checks every link on the interval set under `[health]` in `Rocket.toml`.
+pub struct LinkChecker;
+
+#[rocket::async_trait]
+impl Fairing for LinkChecker {
+    fn info(&self) -> Info {
+        Info {
+            name: "Link Checker",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let Some(pool) = BloggerDatabase::fetch(rocket).map(|db| (**db).clone()) else {
+            return;
+        };
+        let config = match HealthConfig::from_figment(rocket.figment()) {
+            Ok(config) => config,
+            Err(e) => return log::error!("invalid [health] config, not checking links: {e}"),
+        };
+        if config.interval_hours == 0 {
+            return;
+        }
+        let checker = match Checker::new(&config) {
+            Ok(checker) => checker,
+            Err(e) => return log::error!("cannot start the link checker: {e}"),
+        };
+        let period = Duration::from_secs(config.interval_hours * 60 * 60);
+        let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+        tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+                match check_links(&checker, &pool).await {
+                    Ok(checks) => {
+                        let problems = checks.iter().filter(|c| c.problem().is_some()).count();
+                        log::info!("checked {} link(s), {problems} with problems", checks.len());
+                    }
+                    Err(e) => log::error!("cannot check links: {e}"),
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    /// A stand-in HTTP server answering each path with a canned response.
+    async fn serve() -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+                tokio::spawn(async move {
+                    let mut request = vec![0; 1024];
+                    let read = stream.read(&mut request).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&request[..read]);
+                    let mut line = request.split_whitespace();
+                    let (method, path) = (line.next().unwrap_or(""), line.next().unwrap_or(""));
+                    let (status, location) = match (method, path) {
+                        (_, "/ok") => ("200 OK", None),
+                        (_, "/gone") => ("404 Not Found", None),
+                        (_, "/moved") => ("301 Moved Permanently", Some("/ok")),
+                        (_, "/moved-twice") => ("308 Permanent Redirect", Some("/moved")),
+                        (_, "/found") => ("302 Found", Some("/moved")),
+                        (_, "/loop") => ("301 Moved Permanently", Some("/loop")),
+                        ("HEAD", "/no-head") => ("405 Method Not Allowed", None),
+                        (_, "/no-head") => ("200 OK", None),
+                        (_, "/slow") => {
+                            tokio::time::sleep(Duration::from_secs(5)).await;
+                            ("200 OK", None)
+                        }
+                        _ => ("500 Internal Server Error", None),
+                    };
+                    let location = location
+                        .map(|location| format!("Location: {location}\r\n"))
+                        .unwrap_or_default();
+                    let response = format!(
+                        "HTTP/1.1 {status}\r\n{location}Content-Length: 0\r\nConnection: close\r\n\r\n"
+                    );
+                    _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        base
+    }
+
+    fn checker() -> Checker {
+        Checker::new(&HealthConfig {
+            timeout_ms: 500,
+            max_redirects: 3,
+            concurrency: 2,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[rocket::async_test]
+    async fn checks() {
+        let base = serve().await;
+        let checker = checker();
+        let paths = [
+            "ok",
+            "gone",
+            "moved",
+            "moved-twice",
+            "found",
+            "loop",
+            "no-head",
+            "slow",
+        ];
+        let urls = paths.map(|path| base.join(path).unwrap()).to_vec();
+        let checks = checker.check_all(urls).await;
+        let summary = checks
+            .iter()
+            .map(|check| {
+                (
+                    check.status,
+                    check.final_url.as_ref().map(|url| url.path().to_string()),
+                    check.problem(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let ok = Some("/ok".to_string());
+        assert_eq!(
+            summary,
+            [
+                (Some(200), None, None),
+                (Some(404), None, Some(LinkProblem::Broken)),
+                (Some(200), ok.clone(), Some(LinkProblem::Moved)),
+                (Some(200), ok.clone(), Some(LinkProblem::Moved)),
+                (Some(200), ok, None),
+                (Some(301), None, Some(LinkProblem::Broken)),
+                (Some(200), None, None),
+                (None, None, Some(LinkProblem::Broken)),
+            ]
+        );
+        assert_eq!(checks[5].error.as_deref(), Some("Too Many Redirects"));
+        assert_eq!(checks[7].error.as_deref(), Some("Timed Out"));
+        assert_eq!(checks[0].url, base.join("ok").unwrap());
+    }
+
+    #[test]
+    fn config() {
+        use rocket::figment::providers::{Format, Toml};
+
+        let config = |toml: &str| HealthConfig::from_figment(&Figment::from(Toml::string(toml)));
+        assert_eq!(config("").unwrap().interval_hours, 24);
+        let parsed = config("[health]\ninterval_hours = 0").unwrap();
+        assert_eq!(parsed.interval_hours, 0);
+        assert_eq!(parsed.concurrency, 8);
+        assert!(config("[health]\ninterval_hours = \"daily\"").is_err());
+    }
+    #[test]
+    fn report() {
+        let project: Project = serde_json::from_value(serde_json::json!({
+            "id": 3,
+            "title": "Project",
+            "description": "Description",
+            "cover": "https://example.com/cover.png",
+            "tags": ["Rust"],
+            "links": [
+                { "name": "Source", "link": "https://example.com/moved" },
+                { "name": "Demo", "link": "https://example.com/ok" }
+            ]
+        }))
+        .unwrap();
+        let urls = urls(std::slice::from_ref(&project));
+        assert_eq!(urls.len(), 3);
+
+        let check = |path: &str, status: u16, moved: bool| LinkCheck {
+            status: Some(status),
+            permanent_redirect: moved,
+            final_url: moved.then(|| Url::parse("https://example.org/").unwrap()),
+            ..LinkCheck::new(&Url::parse(&format!("https://example.com/{path}")).unwrap())
+        };
+        let report = HealthReport::new(
+            &[project],
+            vec![
+                check("moved", 200, true),
+                check("ok", 200, false),
+                check("cover.png", 404, false),
+            ],
+        );
+        let links = report
+            .links
+            .iter()
+            .map(|link| (link.project, link.link.as_deref(), link.problem))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            links,
+            [
+                (3, None, LinkProblem::Broken),
+                (3, Some("Source"), LinkProblem::Moved)
+            ]
+        );
+    }
+}
@@ -2,22 +2,67 @@ use crate::errors::*;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
+pub mod api;
 pub mod builders;
+pub mod cli;
+pub mod content;
 pub mod db;
+pub mod diff;
 pub mod errors;
+pub mod etag;
+pub mod filter;
+pub mod graph;
+pub mod health;
+pub mod links;
+pub mod order;
+pub mod patch;
+pub mod problem;
+pub mod providers;
+pub mod query;
+pub mod refresh;
+pub mod related;
+pub mod revisions;
+pub mod rewrite;
+pub mod search;
+pub mod sync;
+pub mod tags;
+pub mod trash;
+pub mod urls;
+pub mod validation;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(try_from = "builders::ProjectBuilder")]
 pub struct Project {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<u32>,
     title: String,
     description: String,
     cover: Option<Url>,
     tags: Vec<String>,
     links: Vec<Link>,
+    /// Shown before every project that is not.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    featured: bool,
+    /// Manual ordering among projects, lowest first. Assigned when the project is first saved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    position: Option<u32>,
 }
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(try_from = "builders::LinkBuilder")]
 pub struct Link {
     name: String,
     link: Url,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    role: Option<links::LinkRole>,
+    /// Links with an order come first, lowest first, followed by the others as added.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    order: Option<u32>,
+    /// At most one link per project is primary.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    primary: bool,
+    /// Derived from `link` whenever the link is built.
+    #[serde(flatten)]
+    provider: Option<providers::Provider>,
 }
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Projects {
@@ -65,10 +110,20 @@ mod tests {
     #[test]
     fn no_name_link() {
         let link = LinkBuilder::new()
-            .url(Url::parse("https://google.com").unwrap())
-            .bulid();
-        assert!(link.is_err());
+            .url(Url::parse("https://github.com/itscrystalline/blogger").unwrap())
+            .bulid()
+            .unwrap();
+        assert_eq!(link.name, "GitHub");
+        assert_eq!(link.provider.unwrap().icon, "github");
+
+        let link = LinkBuilder::new()
+            .url(Url::parse("https://www.google.com").unwrap())
+            .bulid()
+            .unwrap();
+        assert_eq!(link.name, "google.com");
+        assert_eq!(link.provider, None);
 
+        let link = LinkBuilder::new().bulid();
         assert!(matches!(link, Err(LinkBuilderError::Name)));
     }
     #[test]
@@ -430,4 +485,64 @@ mod tests {
         assert_eq!(proj.links[0], LinkBuilder::sample());
         assert_eq!(proj.tags[0], "hai");
     }
+    #[test]
+    fn duplicate_links() {
+        let link = |name: &str, url: &str| {
+            LinkBuilder::new()
+                .name(name)
+                .url(Url::parse(url).unwrap())
+                .bulid()
+                .unwrap()
+        };
+        let mut builder = ProjectBuilder::new();
+        builder
+            .title("Project")
+            .description("Description")
+            .add_tag("Rust")
+            .cover(Url::parse("https://Example.com/a.png?utm_source=x").unwrap())
+            .add_link(link("Source", "https://github.com/a/b?utm_source=x"));
+        let project = builder.bulid().unwrap();
+        assert_eq!(project.cover.unwrap().as_str(), "https://example.com/a.png");
+        assert_eq!(project.links[0].link.as_str(), "https://github.com/a/b");
+
+        builder.add_link(link("Code", "https://GITHUB.com:443/a/b?fbclid=1"));
+        assert_eq!(builder.bulid(), Err(ProjectBuilderError::DuplicateLinks));
+    }
+    #[test]
+    fn invalid_json() {
+        let no_tags = r#"{ "title": "a", "description": "b", "cover": null, "tags": [],
+            "links": [{ "name": "Example", "link": "https://example.com" }] }"#;
+        let error = serde_json::from_str::<Project>(no_tags).unwrap_err();
+        assert!(error.to_string().contains("Missing at least one Tag"));
+
+        let empty_name = r#"{ "name": "", "link": "https://example.com" }"#;
+        let error = serde_json::from_str::<Link>(empty_name).unwrap_err();
+        assert!(error.to_string().contains("Missing Link name"));
+
+        let bad_link = r#"{ "title": "a", "description": "b", "tags": ["c"],
+            "links": [{ "name": "Example" }] }"#;
+        assert!(serde_json::from_str::<Project>(bad_link).is_err());
+    }
+    #[test]
+    fn typed_builder() {
+        let project = Project::builder()
+            .add_tag("Rust")
+            .description("portfolio backend")
+            .add_link(LinkBuilder::sample())
+            .title("blogger")
+            .cover(Url::parse("https://example.com/cover.png").unwrap())
+            .id(4)
+            .build()
+            .unwrap();
+        let expected = ProjectBuilder::new()
+            .id(4)
+            .title("blogger")
+            .description("portfolio backend")
+            .cover(Url::parse("https://example.com/cover.png").unwrap())
+            .add_tag("Rust")
+            .add_link(LinkBuilder::sample())
+            .bulid()
+            .unwrap();
+        assert_eq!(project, expected);
+    }
 }
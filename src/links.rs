@@ -0,0 +1,215 @@
+//! What each link of a project is for, and the buttons frontends render from them.
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{Link, Project};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkRole {
+    Source,
+    Demo,
+    Docs,
+    Download,
+    Article,
+    Video,
+}
+
+impl LinkRole {
+    pub const ALL: [LinkRole; 6] = [
+        LinkRole::Source,
+        LinkRole::Demo,
+        LinkRole::Docs,
+        LinkRole::Download,
+        LinkRole::Article,
+        LinkRole::Video,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LinkRole::Source => "source",
+            LinkRole::Demo => "demo",
+            LinkRole::Docs => "docs",
+            LinkRole::Download => "download",
+            LinkRole::Article => "article",
+            LinkRole::Video => "video",
+        }
+    }
+    pub fn parse(role: &str) -> Option<LinkRole> {
+        LinkRole::ALL
+            .into_iter()
+            .find(|known| known.as_str().eq_ignore_ascii_case(role.trim()))
+    }
+
+    /// Text of the button for a link with this role.
+    pub fn label(self) -> &'static str {
+        match self {
+            LinkRole::Source => "View source",
+            LinkRole::Demo => "Live demo",
+            LinkRole::Docs => "Read the docs",
+            LinkRole::Download => "Download",
+            LinkRole::Article => "Read article",
+            LinkRole::Video => "Watch video",
+        }
+    }
+}
+
+impl fmt::Display for LinkRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for Link {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}>", self.link)?;
+        if let Some(role) = self.role {
+            write!(f, " ({role})")?;
+        }
+        if self.primary {
+            f.write_str(" primary")?;
+        }
+        Ok(())
+    }
+}
+
+/// A link as a frontend should render it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Button {
+    pub label: String,
+    pub url: Url,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<LinkRole>,
+    pub primary: bool,
+}
+
+impl Link {
+    /// The role's label, or the link's own name if it has no role.
+    pub fn label(&self) -> &str {
+        match self.role {
+            Some(role) => role.label(),
+            None => &self.name,
+        }
+    }
+}
+
+impl Project {
+    /// The link marked primary, or else the first one.
+    pub fn primary_link(&self) -> Option<&Link> {
+        self.links
+            .iter()
+            .find(|link| link.primary)
+            .or(self.links.first())
+    }
+    /// The first link with `role`.
+    pub fn link_with_role(&self, role: LinkRole) -> Option<&Link> {
+        self.links.iter().find(|link| link.role == Some(role))
+    }
+
+    /// One button per link, the primary one first and the rest in order.
+    pub fn buttons(&self) -> Vec<Button> {
+        let primary = self.primary_link();
+        let button = |link: &Link| Button {
+            label: link.label().to_string(),
+            url: link.link.clone(),
+            role: link.role,
+            primary: primary == Some(link),
+        };
+        primary
+            .into_iter()
+            .chain(self.links.iter().filter(|link| Some(*link) != primary))
+            .map(button)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builders::{LinkBuilder, ProjectBuilder};
+
+    fn link(name: &str, url: &str, role: Option<LinkRole>) -> LinkBuilder {
+        let mut builder = LinkBuilder::new();
+        builder.name(name).url(Url::parse(url).unwrap());
+        if let Some(role) = role {
+            builder.role(role);
+        }
+        builder
+    }
+
+    /// [`ProjectBuilder::sample`] with `links` instead of its own.
+    fn with_links(links: &[LinkBuilder]) -> Result<Project, crate::ProjectBuilderError> {
+        let mut builder = ProjectBuilder::sample(&["Rust"]);
+        builder.remove_link("Example");
+        for link in links {
+            builder.add_link(link.bulid().unwrap());
+        }
+        builder.bulid()
+    }
+
+    #[test]
+    fn roles() {
+        assert_eq!(LinkRole::parse(" Demo"), Some(LinkRole::Demo));
+        assert_eq!(LinkRole::parse("blog"), None);
+        for role in LinkRole::ALL {
+            assert_eq!(LinkRole::parse(role.as_str()), Some(role));
+        }
+    }
+    #[test]
+    fn buttons() {
+        let project = with_links(&[
+            link("GitHub", "https://github.com/a/b", Some(LinkRole::Source)),
+            link("Blog post", "https://example.com/post", None),
+            link("Play", "https://a.github.io/b", Some(LinkRole::Demo))
+                .primary(true)
+                .clone(),
+        ])
+        .unwrap();
+        assert_eq!(project.primary_link().unwrap().name, "Play");
+        assert_eq!(
+            project.link_with_role(LinkRole::Source).unwrap().name,
+            "GitHub"
+        );
+        let labels = project
+            .buttons()
+            .into_iter()
+            .map(|button| (button.label, button.primary))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            [
+                ("Live demo".to_string(), true),
+                ("View source".to_string(), false),
+                ("Blog post".to_string(), false)
+            ]
+        );
+    }
+    #[test]
+    fn order_and_primary() {
+        let ordered = with_links(&[
+            link("c", "https://example.com/c", None),
+            link("b", "https://example.com/b", None).order(2).clone(),
+            link("a", "https://example.com/a", None).order(1).clone(),
+        ])
+        .unwrap();
+        let names = ordered
+            .links
+            .iter()
+            .map(|link| link.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert_eq!(ordered.primary_link().unwrap().name, "a");
+
+        let twice = with_links(&[
+            link("a", "https://example.com/a", None)
+                .primary(true)
+                .clone(),
+            link("b", "https://example.com/b", None)
+                .primary(true)
+                .clone(),
+        ]);
+        assert_eq!(twice, Err(crate::ProjectBuilderError::PrimaryLinks));
+    }
+}
@@ -1,13 +1,37 @@
-use blogger::db;
+use blogger::{
+    api, cli, content, db, health, problem, refresh, related, search, tags, trash, validation,
+};
+use rocket::{Build, Rocket};
 use rocket_db_pools::Database;
 
 #[macro_use]
 extern crate rocket;
-#[launch]
-fn entry() -> _ {
+
+fn server() -> Rocket<Build> {
     rocket::build()
+        .attach(validation::Validation)
         .attach(db::BloggerDatabase::init())
+        .attach(content::ContentDir)
+        .attach(trash::TrashPurger)
+        .attach(tags::TagLoader)
+        .attach(search::Indexer)
+        .attach(related::Recommender)
+        .attach(health::LinkChecker)
+        .attach(refresh::Refresher)
+        .attach(api::AdminToken)
         .mount("/", routes![index])
+        .mount("/api", api::routes())
+        .register("/", problem::catchers())
+}
+
+#[rocket::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return Ok(cli::run(&args).await?);
+    }
+    server().launch().await?;
+    Ok(())
 }
 
 #[get("/")]
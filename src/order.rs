@@ -0,0 +1,171 @@
+//! Manual ordering of projects.
+//!
+//! Every project has a position, and lists show featured projects first and then the rest, each
+//! by position. Reordering renumbers the live projects from 1 in a single transaction.
+use std::collections::HashSet;
+
+use rocket_db_pools::sqlx::{self, Connection, MySqlConnection, Row, mysql::MySqlRow};
+use serde::Deserialize;
+
+use crate::{OrderError, db::ORDER};
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Reorder {
+    /// Moves project `id` right before project `target`.
+    Before { id: u32, target: u32 },
+    /// Moves project `id` right after project `target`.
+    After { id: u32, target: u32 },
+    /// Puts the projects in `ids` first, in that order, followed by any others as they were.
+    Order { ids: Vec<u32> },
+}
+
+impl Reorder {
+    /// `current`, a list of project ids, in the new order.
+    pub fn reorder(&self, current: &[u32]) -> Result<Vec<u32>, OrderError> {
+        let check = |id: &u32| {
+            current
+                .contains(id)
+                .then_some(*id)
+                .ok_or(OrderError::NotFound(*id))
+        };
+        match self {
+            Reorder::Before { id, target } | Reorder::After { id, target } => {
+                check(id)?;
+                check(target)?;
+                if id == target {
+                    return Ok(current.to_vec());
+                }
+                let mut order = current.to_vec();
+                order.retain(|other| other != id);
+                let mut at = order.iter().position(|other| other == target).unwrap();
+                if matches!(self, Reorder::After { .. }) {
+                    at += 1;
+                }
+                order.insert(at, *id);
+                Ok(order)
+            }
+            Reorder::Order { ids } => {
+                let mut order = Vec::with_capacity(current.len());
+                for id in ids {
+                    if order.contains(&check(id)?) {
+                        return Err(OrderError::Duplicate(*id));
+                    }
+                    order.push(*id);
+                }
+                order.extend(current.iter().filter(|id| !ids.contains(id)));
+                Ok(order)
+            }
+        }
+    }
+
+    /// Like [`Reorder::reorder`] for `current` ids paired with whether they are featured, listed
+    /// featured first. Featured projects stay first, so a move only reorders within its group.
+    pub fn reorder_grouped(&self, current: &[(u32, bool)]) -> Result<Vec<u32>, OrderError> {
+        let ids = current.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        let mut order = self.reorder(&ids)?;
+        let featured = current
+            .iter()
+            .filter(|(_, featured)| *featured)
+            .map(|(id, _)| *id)
+            .collect::<HashSet<_>>();
+        order.sort_by_key(|id| !featured.contains(id));
+        Ok(order)
+    }
+
+    /// Reorders the live projects and returns their ids in the order they are now listed.
+    pub async fn apply(&self, db: &mut MySqlConnection) -> Result<Vec<u32>, OrderError> {
+        let mut tx = db.begin().await?;
+        let current = sqlx::query(&format!(
+            "SELECT id, featured, position FROM projects WHERE deleted_at IS NULL \
+             ORDER BY {ORDER} FOR UPDATE"
+        ))
+        .map(|row: MySqlRow| {
+            (
+                row.get::<u32, _>("id"),
+                row.get::<bool, _>("featured"),
+                row.get::<u32, _>("position"),
+            )
+        })
+        .fetch_all(&mut *tx)
+        .await?;
+        let order = self.reorder_grouped(
+            &current
+                .iter()
+                .map(|(id, featured, _)| (*id, *featured))
+                .collect::<Vec<_>>(),
+        )?;
+        for (i, id) in order.iter().enumerate() {
+            let position = i as u32 + 1;
+            if current
+                .iter()
+                .any(|(other, _, current)| other == id && *current == position)
+            {
+                continue;
+            }
+            sqlx::query("UPDATE projects SET position = ? WHERE id = ?")
+                .bind(position)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURRENT: [u32; 4] = [1, 2, 3, 4];
+
+    #[test]
+    fn moves() {
+        let before = Reorder::Before { id: 4, target: 2 };
+        assert_eq!(before.reorder(&CURRENT).unwrap(), [1, 4, 2, 3]);
+        let after = Reorder::After { id: 1, target: 3 };
+        assert_eq!(after.reorder(&CURRENT).unwrap(), [2, 3, 1, 4]);
+        let after_last = Reorder::After { id: 2, target: 4 };
+        assert_eq!(after_last.reorder(&CURRENT).unwrap(), [1, 3, 4, 2]);
+        let itself = Reorder::Before { id: 3, target: 3 };
+        assert_eq!(itself.reorder(&CURRENT).unwrap(), CURRENT);
+        assert!(matches!(
+            Reorder::Before { id: 9, target: 1 }.reorder(&CURRENT),
+            Err(OrderError::NotFound(9))
+        ));
+    }
+    #[test]
+    fn full_order() {
+        let order = Reorder::Order {
+            ids: vec![3, 1, 4, 2],
+        };
+        assert_eq!(order.reorder(&CURRENT).unwrap(), [3, 1, 4, 2]);
+        let partial = Reorder::Order { ids: vec![4, 2] };
+        assert_eq!(partial.reorder(&CURRENT).unwrap(), [4, 2, 1, 3]);
+        assert!(matches!(
+            Reorder::Order { ids: vec![2, 2] }.reorder(&CURRENT),
+            Err(OrderError::Duplicate(2))
+        ));
+        assert!(matches!(
+            Reorder::Order { ids: vec![5] }.reorder(&CURRENT),
+            Err(OrderError::NotFound(5))
+        ));
+    }
+    #[test]
+    fn featured_first() {
+        let current = [(2, true), (4, true), (1, false), (3, false)];
+        let before = Reorder::Before { id: 3, target: 2 };
+        assert_eq!(before.reorder_grouped(&current).unwrap(), [2, 4, 3, 1]);
+        let order = Reorder::Order { ids: vec![1, 4] };
+        assert_eq!(order.reorder_grouped(&current).unwrap(), [4, 2, 1, 3]);
+    }
+    #[test]
+    fn json() {
+        let reorder: Reorder =
+            serde_json::from_str(r#"{"op": "after", "id": 2, "target": 5}"#).unwrap();
+        assert_eq!(reorder, Reorder::After { id: 2, target: 5 });
+        let reorder: Reorder = serde_json::from_str(r#"{"op": "order", "ids": [3, 1]}"#).unwrap();
+        assert_eq!(reorder, Reorder::Order { ids: vec![3, 1] });
+    }
+}
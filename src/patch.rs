@@ -0,0 +1,488 @@
+//! Partial updates of a [`Project`], applied through its [`ProjectBuilder`] so the usual builder
+//! rules still hold.
+//!
+//! Supports RFC 7396 JSON Merge Patch documents and RFC 6902 JSON Patch documents. JSON Patch
+//! paths address `/title`, `/description`, `/cover`, `/featured`, `/position`, `/tags`,
+//! `/tags/<index>`, `/links`, `/links/<index>` and `/links/<index>/name` or `/links/<index>/link`;
+//! `move` and `copy` are applied as a `remove` and an `add`, or as an `add` of the value at `from`.
+//! A JSON Patch stops at the first operation that fails and is not applied at all.
+use serde::Deserialize;
+use serde_json::Value;
+use url::Url;
+
+use crate::{
+    Link, PatchError, Project,
+    builders::{Edit, LinkBuilder, ProjectBuilder},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Patch {
+    Merge(Value),
+    Json(Value),
+}
+
+#[derive(Debug, Deserialize)]
+struct Operation {
+    op: String,
+    path: String,
+    value: Option<Value>,
+    from: Option<String>,
+}
+
+fn string(path: &str, value: &Value) -> Result<String, PatchError> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| PatchError::Value(path.to_string()))
+}
+fn boolean(path: &str, value: &Value) -> Result<bool, PatchError> {
+    value
+        .as_bool()
+        .ok_or_else(|| PatchError::Value(path.to_string()))
+}
+fn position(path: &str, value: &Value) -> Result<u32, PatchError> {
+    value
+        .as_u64()
+        .and_then(|position| u32::try_from(position).ok())
+        .ok_or_else(|| PatchError::Value(path.to_string()))
+}
+fn url(path: &str, value: &Value) -> Result<Url, PatchError> {
+    value
+        .as_str()
+        .and_then(|url| Url::parse(url).ok())
+        .ok_or_else(|| PatchError::Value(path.to_string()))
+}
+fn link(index: usize, value: &Value) -> Result<Link, PatchError> {
+    LinkBuilder::deserialize(value)
+        .map_err(|_| PatchError::Value(format!("/links/{index}")))?
+        .bulid()
+        .map_err(|e| PatchError::Link(index, e))
+}
+fn array<T>(
+    path: &str,
+    value: &Value,
+    item: impl Fn(&str, usize, &Value) -> Result<T, PatchError>,
+) -> Result<Vec<T>, PatchError> {
+    let Value::Array(items) = value else {
+        return Err(PatchError::Value(path.to_string()));
+    };
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, value)| item(&format!("{path}/{index}"), index, value))
+        .collect()
+}
+
+fn tag(path: &str, _: usize, value: &Value) -> Result<String, PatchError> {
+    string(path, value)
+}
+fn link_at(_: &str, index: usize, value: &Value) -> Result<Link, PatchError> {
+    link(index, value)
+}
+
+fn set_tags(builder: &mut ProjectBuilder, tags: Vec<String>) -> &mut ProjectBuilder {
+    for tag in builder.tags().to_vec() {
+        builder.remove_tag(&tag);
+    }
+    for tag in tags {
+        builder.add_tag(&tag);
+    }
+    builder
+}
+fn set_links(builder: &mut ProjectBuilder, links: Vec<Link>) -> &mut ProjectBuilder {
+    for link in builder.links().to_vec() {
+        builder.remove_link(&link.name);
+    }
+    for link in links {
+        builder.add_link(link);
+    }
+    builder
+}
+
+fn merge_patch(builder: &mut ProjectBuilder, patch: &Value) -> Vec<PatchError> {
+    let Value::Object(patch) = patch else {
+        return vec![PatchError::Document];
+    };
+    patch
+        .iter()
+        .filter_map(|(key, value)| {
+            let path = format!("/{key}");
+            let result = match (key.as_str(), value) {
+                ("title" | "description" | "tags" | "links", Value::Null) => {
+                    Err(PatchError::Value(path))
+                }
+                ("title", value) => string(&path, value).map(|title| _ = builder.title(&title)),
+                ("description", value) => {
+                    string(&path, value).map(|description| _ = builder.description(&description))
+                }
+                ("cover", Value::Null) => Ok(_ = builder.remove_cover()),
+                ("cover", value) => url(&path, value).map(|cover| _ = builder.cover(cover)),
+                ("featured", Value::Null) => Ok(_ = builder.featured(false)),
+                ("featured", value) => {
+                    boolean(&path, value).map(|featured| _ = builder.featured(featured))
+                }
+                ("position", Value::Null) => Ok(_ = builder.remove_position()),
+                ("position", value) => {
+                    position(&path, value).map(|position| _ = builder.position(position))
+                }
+                ("tags", value) => array(&path, value, tag).map(|tags| _ = set_tags(builder, tags)),
+                ("links", value) => {
+                    array(&path, value, link_at).map(|links| _ = set_links(builder, links))
+                }
+                _ => Err(PatchError::Path(path)),
+            };
+            result.err()
+        })
+        .collect()
+}
+
+/// Resolves `/<collection>/<index>`, where `-` or the length itself means one past the end.
+fn index(path: &str, segment: &str, len: usize, appending: bool) -> Result<usize, PatchError> {
+    let index = match segment {
+        "-" if appending => len,
+        _ => segment
+            .parse()
+            .map_err(|_| PatchError::Path(path.to_string()))?,
+    };
+    if index < len || (appending && index == len) {
+        Ok(index)
+    } else {
+        Err(PatchError::Path(path.to_string()))
+    }
+}
+
+/// Applies `move` or `copy` as the operations they are made of.
+fn move_or_copy(builder: &mut ProjectBuilder, operation: &Operation) -> Result<(), PatchError> {
+    let Operation { op, path, from, .. } = operation;
+    let from = from.as_ref().ok_or(PatchError::Document)?;
+    let current = serde_json::to_value(&*builder).expect("builders always serialize");
+    let value = current
+        .pointer(from)
+        .cloned()
+        .ok_or_else(|| PatchError::Path(from.clone()))?;
+    let add = |value| Operation {
+        op: "add".to_string(),
+        path: path.clone(),
+        value: Some(value),
+        from: None,
+    };
+    if op == "copy" {
+        return apply_operation(builder, &add(value));
+    }
+    if from == path {
+        return Ok(());
+    }
+    if path.starts_with(&format!("{from}/")) {
+        return Err(PatchError::Path(path.clone()));
+    }
+    let remove = Operation {
+        op: "remove".to_string(),
+        path: from.clone(),
+        value: None,
+        from: None,
+    };
+    apply_operation(builder, &remove)?;
+    apply_operation(builder, &add(value))
+}
+
+fn apply_operation(builder: &mut ProjectBuilder, operation: &Operation) -> Result<(), PatchError> {
+    let Operation {
+        op, path, value, ..
+    } = operation;
+    if matches!(op.as_str(), "move" | "copy") {
+        return move_or_copy(builder, operation);
+    }
+    let segments: Vec<String> = path
+        .strip_prefix('/')
+        .ok_or_else(|| PatchError::Path(path.clone()))?
+        .split('/')
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    let value = || {
+        value
+            .as_ref()
+            .ok_or_else(|| PatchError::Value(path.clone()))
+    };
+
+    match (op.as_str(), segments.as_slice()) {
+        ("test", _) => {
+            let current = serde_json::to_value(&*builder).expect("builders always serialize");
+            if current.pointer(path) == Some(value()?) {
+                Ok(())
+            } else {
+                Err(PatchError::Test(path.clone()))
+            }
+        }
+        ("add" | "replace", ["title"]) => Ok(_ = builder.title(&string(path, value()?)?)),
+        ("add" | "replace", ["description"]) => {
+            Ok(_ = builder.description(&string(path, value()?)?))
+        }
+        ("add" | "replace", ["cover"]) => Ok(_ = builder.cover(url(path, value()?)?)),
+        ("remove", ["cover"]) => Ok(_ = builder.remove_cover()),
+        ("add" | "replace", ["featured"]) => Ok(_ = builder.featured(boolean(path, value()?)?)),
+        ("remove", ["featured"]) => Ok(_ = builder.featured(false)),
+        ("add" | "replace", ["position"]) => Ok(_ = builder.position(position(path, value()?)?)),
+        ("remove", ["position"]) => Ok(_ = builder.remove_position()),
+        ("add" | "replace", ["tags"]) => Ok(_ = set_tags(builder, array(path, value()?, tag)?)),
+        ("add" | "replace", ["links"]) => {
+            Ok(_ = set_links(builder, array(path, value()?, link_at)?))
+        }
+        ("add" | "replace" | "remove", ["tags", at]) => {
+            let mut tags = builder.tags().to_vec();
+            let at = index(path, at, tags.len(), op == "add")?;
+            match op.as_str() {
+                "add" => tags.insert(at, string(path, value()?)?),
+                "replace" => tags[at] = string(path, value()?)?,
+                _ => _ = tags.remove(at),
+            }
+            Ok(_ = set_tags(builder, tags))
+        }
+        ("add" | "replace" | "remove", ["links", at]) => {
+            let mut links = builder.links().to_vec();
+            let at = index(path, at, links.len(), op == "add")?;
+            match op.as_str() {
+                "add" => links.insert(at, link(at, value()?)?),
+                "replace" => links[at] = link(at, value()?)?,
+                _ => _ = links.remove(at),
+            }
+            Ok(_ = set_links(builder, links))
+        }
+        ("replace", ["links", at, field @ ("name" | "link")]) => {
+            let mut links = builder.links().to_vec();
+            let at = index(path, at, links.len(), false)?;
+            let mut link = links[at].clone().edit();
+            match *field {
+                "name" => _ = link.name(&string(path, value()?)?),
+                _ => _ = link.url(url(path, value()?)?),
+            }
+            links[at] = link.bulid().map_err(|e| PatchError::Link(at, e))?;
+            Ok(_ = set_links(builder, links))
+        }
+        ("add" | "replace" | "remove", _) => Err(PatchError::Path(path.clone())),
+        (op, _) => Err(PatchError::Operation(op.to_string())),
+    }
+}
+
+/// Applies the operations in order, stopping at the first one that fails.
+fn json_patch(builder: &mut ProjectBuilder, patch: &Value) -> Result<(), PatchError> {
+    let operations = Vec::<Operation>::deserialize(patch).map_err(|_| PatchError::Document)?;
+    operations
+        .iter()
+        .try_for_each(|operation| apply_operation(builder, operation))
+}
+
+impl Patch {
+    /// Applies the patch to `project`, reporting every problem with the patch and the result at
+    /// once. A JSON Patch only reports the operation it stopped at.
+    pub fn apply(&self, project: Project) -> Result<Project, Vec<PatchError>> {
+        let mut builder = project.edit();
+        let mut errors = match self {
+            Patch::Merge(patch) => merge_patch(&mut builder, patch),
+            Patch::Json(patch) => {
+                json_patch(&mut builder, patch).map_err(|e| vec![e])?;
+                vec![]
+            }
+        };
+        errors.extend(builder.validate().into_iter().map(PatchError::from));
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        builder.bulid().map_err(|e| vec![e.into()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{LinkBuilderError, ProjectBuilderError, links::LinkRole};
+
+    #[test]
+    fn merge_patch_fields() {
+        let project = ProjectBuilder::sample(&["Rust", "MySQL"])
+            .id(1)
+            .cover(Url::parse("https://example.com/cover.png").unwrap())
+            .bulid()
+            .unwrap();
+        let patch = Patch::Merge(json!({
+            "title": "blogger 2",
+            "cover": null,
+            "tags": ["Rust", "Rocket"],
+        }));
+        let patched = patch.apply(project).unwrap();
+        assert_eq!(patched.id, Some(1));
+        assert_eq!(patched.title, "blogger 2");
+        assert_eq!(patched.description, "Description");
+        assert_eq!(patched.cover, None);
+        assert_eq!(patched.tags, vec!["Rust", "Rocket"]);
+
+        let patched = Patch::Merge(json!({ "featured": true, "position": 2 }))
+            .apply(patched)
+            .unwrap();
+        assert!(patched.featured);
+        assert_eq!(patched.position, Some(2));
+        let patched = Patch::Merge(json!({ "featured": null, "position": null }))
+            .apply(patched)
+            .unwrap();
+        assert!(!patched.featured);
+        assert_eq!(patched.position, None);
+        assert_eq!(
+            Patch::Merge(json!({ "position": -1 })).apply(patched),
+            Err(vec![PatchError::Value("/position".to_string())])
+        );
+    }
+    #[test]
+    fn merge_patch_errors_are_aggregated() {
+        let project = ProjectBuilder::sample(&["Rust", "MySQL"])
+            .id(1)
+            .cover(Url::parse("https://example.com/cover.png").unwrap())
+            .bulid()
+            .unwrap();
+        let patch = Patch::Merge(json!({
+            "title": null,
+            "tags": [],
+            "links": [{ "name": "", "link": "https://example.com" }],
+            "stars": 5,
+        }));
+        let errors = patch.apply(project).unwrap_err();
+        assert!(errors.contains(&PatchError::Value("/title".to_string())));
+        assert!(errors.contains(&PatchError::Link(0, LinkBuilderError::Name)));
+        assert_eq!(
+            PatchError::Link(0, LinkBuilderError::Name).pointer(),
+            "/links/0/name"
+        );
+        assert!(errors.contains(&PatchError::Path("/stars".to_string())));
+        assert!(errors.contains(&PatchError::Project(ProjectBuilderError::Tags)));
+    }
+    #[test]
+    fn json_patch_operations() {
+        let project = ProjectBuilder::sample(&["Rust", "MySQL"])
+            .id(1)
+            .cover(Url::parse("https://example.com/cover.png").unwrap())
+            .bulid()
+            .unwrap();
+        let patch = Patch::Json(json!([
+            { "op": "test", "path": "/tags/0", "value": "Rust" },
+            { "op": "remove", "path": "/tags/1" },
+            { "op": "add", "path": "/tags/-", "value": "Rocket" },
+            { "op": "add", "path": "/tags/0", "value": "Web" },
+            { "op": "replace", "path": "/links/0/name", "value": "Homepage" },
+            { "op": "add", "path": "/links/-", "value": { "name": "Docs", "link": "https://docs.rs" } },
+            { "op": "remove", "path": "/cover" },
+        ]));
+        let patched = patch.apply(project).unwrap();
+        assert_eq!(patched.tags, vec!["Web", "Rust", "Rocket"]);
+        assert_eq!(patched.links[0].name, "Homepage");
+        assert_eq!(patched.links[1].link.as_str(), "https://docs.rs/");
+        assert_eq!(patched.cover, None);
+    }
+    #[test]
+    fn links_keep_role_and_primary() {
+        let project = ProjectBuilder::sample(&["Rust"]).bulid().unwrap();
+        let docs =
+            json!({ "name": "Docs", "link": "https://docs.rs/", "role": "docs", "primary": true });
+
+        let merged = Patch::Merge(json!({ "links": [docs] }))
+            .apply(project.clone())
+            .unwrap();
+        assert_eq!(merged.links[0].role, Some(LinkRole::Docs));
+        assert!(merged.links[0].primary);
+        assert_eq!(
+            serde_json::to_value(&merged.links[0]).unwrap()["role"],
+            "docs"
+        );
+
+        let added = Patch::Json(json!([{ "op": "add", "path": "/links/0", "value": docs }]))
+            .apply(project)
+            .unwrap();
+        assert_eq!(added.links[0], merged.links[0]);
+        assert_eq!(
+            Patch::Json(json!([{ "op": "test", "path": "/links/0/primary", "value": true }]))
+                .apply(added.clone())
+                .unwrap(),
+            added
+        );
+    }
+    #[test]
+    fn json_patch_move_and_copy() {
+        let project = ProjectBuilder::sample(&["Rust", "MySQL"])
+            .id(1)
+            .cover(Url::parse("https://example.com/cover.png").unwrap())
+            .bulid()
+            .unwrap();
+        let patch = Patch::Json(json!([
+            { "op": "move", "from": "/tags/0", "path": "/tags/-" },
+            { "op": "copy", "from": "/title", "path": "/description" },
+            { "op": "copy", "from": "/links/0", "path": "/links/-" },
+            { "op": "replace", "path": "/links/1/name", "value": "Mirror" },
+            { "op": "replace", "path": "/links/1/link", "value": "https://mirror.example.com" },
+        ]));
+        let patched = patch.apply(project.clone()).unwrap();
+        assert_eq!(patched.tags, vec!["MySQL", "Rust"]);
+        assert_eq!(patched.description, "Project");
+        assert_eq!(patched.links[1].name, "Mirror");
+        assert_eq!(
+            patched.links[1].link.as_str(),
+            "https://mirror.example.com/"
+        );
+
+        let into_itself = Patch::Json(json!([
+            { "op": "move", "from": "/links/0", "path": "/links/0/name" },
+        ]));
+        assert_eq!(
+            into_itself.apply(project.clone()).unwrap_err(),
+            vec![PatchError::Path("/links/0/name".to_string())]
+        );
+    }
+    #[test]
+    fn json_patch_errors() {
+        let project = ProjectBuilder::sample(&["Rust", "MySQL"])
+            .id(1)
+            .cover(Url::parse("https://example.com/cover.png").unwrap())
+            .bulid()
+            .unwrap();
+        let patch = Patch::Json(json!([
+            { "op": "remove", "path": "/tags/7" },
+            { "op": "test", "path": "/title", "value": "something else" },
+        ]));
+        assert_eq!(
+            patch.apply(project.clone()).unwrap_err(),
+            vec![PatchError::Path("/tags/7".to_string())]
+        );
+        let patch = Patch::Json(json!([
+            { "op": "test", "path": "/title", "value": "something else" },
+            { "op": "replace", "path": "/title", "value": "never applied" },
+        ]));
+        assert_eq!(
+            patch.apply(project.clone()).unwrap_err(),
+            vec![PatchError::Test("/title".to_string())]
+        );
+        let patch = Patch::Json(json!([
+            { "op": "move", "from": "/description", "path": "/title" },
+            { "op": "frobnicate", "path": "/title" },
+        ]));
+        assert_eq!(
+            patch.apply(project.clone()).unwrap_err(),
+            vec![PatchError::Path("/description".to_string())]
+        );
+        let patch = Patch::Json(json!([{ "op": "remove", "path": "/links/0" }]));
+        assert_eq!(
+            patch.apply(project.clone()).unwrap_err(),
+            vec![PatchError::Project(ProjectBuilderError::Links)]
+        );
+        assert_eq!(
+            Patch::Json(json!([{ "op": "frobnicate", "path": "/title" }]))
+                .apply(project.clone())
+                .unwrap_err(),
+            vec![PatchError::Operation("frobnicate".to_string())]
+        );
+        assert_eq!(
+            Patch::Json(json!({ "op": "add" }))
+                .apply(project.clone())
+                .unwrap_err(),
+            vec![PatchError::Document]
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! RFC 9457 `application/problem+json` error responses.
+use rocket::{
+    Catcher, Request, Response, catch, catchers,
+    http::{ContentType, Header, Status},
+    response::{self, Responder},
+    serde::json::Json,
+};
+use serde::Serialize;
+
+use crate::{ApiError, OrderError, Project, RewriteError, TagError};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub detail: String,
+    pub pointer: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub title: String,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+    /// Current version of the resource when a precondition failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<Project>,
+}
+
+impl Problem {
+    /// A problem that is fully described by its status code.
+    pub fn status(status: Status) -> Self {
+        Problem {
+            kind: "about:blank",
+            title: status.reason_lossy().to_string(),
+            status: status.code,
+            detail: None,
+            instance: None,
+            errors: vec![],
+            current: None,
+        }
+    }
+    fn with(mut self, kind: &'static str, detail: impl ToString) -> Self {
+        self.kind = kind;
+        self.detail = Some(detail.to_string());
+        self
+    }
+
+    pub fn content_type() -> ContentType {
+        ContentType::new("application", "problem+json")
+    }
+}
+
+impl ApiError {
+    pub fn status(&self) -> Status {
+        match self {
+            ApiError::NotFound(_) => Status::NotFound,
+            ApiError::Project(_) | ApiError::Patch(_) | ApiError::Tag(TagError::Name(_)) => {
+                Status::UnprocessableEntity
+            }
+            ApiError::PreconditionRequired => Status::PreconditionRequired,
+            ApiError::PreconditionFailed(_) => Status::PreconditionFailed,
+            ApiError::Tag(TagError::NotFound(_)) => Status::NotFound,
+            ApiError::Tag(TagError::Exists(_) | TagError::LastTag(_) | TagError::Cycle(_)) => {
+                Status::Conflict
+            }
+            ApiError::Query(_) | ApiError::Cursor(_) => Status::BadRequest,
+            ApiError::Order(OrderError::NotFound(_)) => Status::NotFound,
+            ApiError::Order(OrderError::Duplicate(_)) => Status::UnprocessableEntity,
+            ApiError::Rewrite(RewriteError::Database(_)) => Status::ServiceUnavailable,
+            ApiError::Rewrite(_) => Status::UnprocessableEntity,
+            ApiError::Tag(TagError::Database(_))
+            | ApiError::Order(OrderError::Database(_))
+            | ApiError::Database(_) => Status::ServiceUnavailable,
+        }
+    }
+
+    pub fn problem(&self) -> Problem {
+        let problem = Problem::status(self.status());
+        match self {
+            ApiError::NotFound(_) => problem.with("/problems/not-found", self),
+            ApiError::Project(e) => Problem {
+                errors: vec![FieldError {
+                    detail: e.to_string(),
+                    pointer: e.pointer().to_string(),
+                }],
+                ..problem.with("/problems/validation", "The project is invalid")
+            },
+            ApiError::Patch(errors) => Problem {
+                errors: errors
+                    .iter()
+                    .map(|e| FieldError {
+                        detail: e.to_string(),
+                        pointer: e.pointer(),
+                    })
+                    .collect(),
+                ..problem.with("/problems/validation", "The patch cannot be applied")
+            },
+            ApiError::PreconditionRequired => problem.with("/problems/precondition", self),
+            ApiError::PreconditionFailed(current) => Problem {
+                current: Some(current.as_ref().clone()),
+                ..problem.with("/problems/precondition", self)
+            },
+            ApiError::Tag(TagError::Name(_)) => problem.with("/problems/validation", self),
+            ApiError::Tag(TagError::NotFound(_)) => problem.with("/problems/not-found", self),
+            ApiError::Tag(TagError::Exists(_) | TagError::LastTag(_) | TagError::Cycle(_)) => {
+                problem.with("/problems/tag-conflict", self)
+            }
+            ApiError::Query(_) => problem.with("/problems/query", self),
+            ApiError::Cursor(_) => problem.with("/problems/cursor", self),
+            ApiError::Order(OrderError::NotFound(_)) => problem.with("/problems/not-found", self),
+            ApiError::Order(OrderError::Duplicate(_)) => problem.with("/problems/validation", self),
+            ApiError::Rewrite(RewriteError::Database(_)) => problem,
+            ApiError::Rewrite(_) => problem.with("/problems/validation", self),
+            // Database details stay in the log.
+            ApiError::Tag(TagError::Database(_))
+            | ApiError::Order(OrderError::Database(_))
+            | ApiError::Database(_) => problem,
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        if let ApiError::Database(e)
+        | ApiError::Tag(TagError::Database(e))
+        | ApiError::Order(OrderError::Database(e))
+        | ApiError::Rewrite(RewriteError::Database(e)) = &self
+        {
+            log::error!("database error: {e}");
+        }
+        let problem = Problem {
+            instance: Some(request.uri().path().to_string()),
+            ..self.problem()
+        };
+        let mut response = Response::build_from(Json(problem).respond_to(request)?);
+        response
+            .status(self.status())
+            .header(Problem::content_type());
+        if let ApiError::PreconditionFailed(current) = &self {
+            response.header(Header::new("ETag", current.etag()));
+        }
+        response.ok()
+    }
+}
+
+/// Problem responses for errors raised outside of handlers, like unknown routes or bodies that
+/// fail to parse.
+pub fn catchers() -> Vec<Catcher> {
+    catchers![not_found, internal_error, default]
+}
+
+fn caught(status: Status, request: &Request<'_>) -> (ContentType, Json<Problem>) {
+    let problem = Problem {
+        instance: Some(request.uri().path().to_string()),
+        ..Problem::status(status)
+    };
+    (Problem::content_type(), Json(problem))
+}
+
+#[catch(404)]
+fn not_found(request: &Request<'_>) -> (ContentType, Json<Problem>) {
+    caught(Status::NotFound, request)
+}
+
+#[catch(500)]
+fn internal_error(request: &Request<'_>) -> (ContentType, Json<Problem>) {
+    caught(Status::InternalServerError, request)
+}
+
+#[catch(default)]
+fn default(status: Status, request: &Request<'_>) -> (Status, (ContentType, Json<Problem>)) {
+    (status, caught(status, request))
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::{get, local::blocking::Client, routes, serde::json::Value};
+
+    use super::*;
+    use crate::{PatchError, ProjectBuilderError};
+
+    #[get("/invalid")]
+    fn invalid() -> Result<(), ApiError> {
+        Err(ProjectBuilderError::Tags.into())
+    }
+    #[get("/patch")]
+    fn patch() -> Result<(), ApiError> {
+        Err(ApiError::Patch(vec![
+            PatchError::Test("/title".to_string()),
+            PatchError::Project(ProjectBuilderError::Links),
+        ]))
+    }
+
+    fn client() -> Client {
+        let rocket = rocket::build()
+            .mount("/", routes![invalid, patch])
+            .register("/", catchers());
+        Client::tracked(rocket).unwrap()
+    }
+
+    #[test]
+    fn validation_problem() {
+        let client = client();
+        let response = client.get("/invalid").dispatch();
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        assert_eq!(response.content_type(), Some(Problem::content_type()));
+        let body: Value = response.into_json().unwrap();
+        assert_eq!(body["type"], "/problems/validation");
+        assert_eq!(body["status"], 422);
+        assert_eq!(body["instance"], "/invalid");
+        assert_eq!(body["errors"][0]["pointer"], "/tags");
+
+        let body: Value = client.get("/patch").dispatch().into_json().unwrap();
+        assert_eq!(body["errors"][0]["pointer"], "/title");
+        assert_eq!(body["errors"][1]["pointer"], "/links");
+    }
+    #[test]
+    fn catchers_render_problems() {
+        let client = client();
+        let response = client.get("/missing").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        assert_eq!(response.content_type(), Some(Problem::content_type()));
+        let body: Value = response.into_json().unwrap();
+        assert_eq!(body["title"], "Not Found");
+        assert_eq!(body["type"], "about:blank");
+    }
+}
@@ -0,0 +1,110 @@
+//! Recognizes well-known hosts that links point at.
+use serde::Serialize;
+use url::Url;
+
+/// Who hosts a link, and the icon frontends should show for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct Provider {
+    #[serde(rename = "provider")]
+    pub name: &'static str,
+    pub icon: &'static str,
+}
+
+/// Domain, provider name and icon key. A domain also matches its subdomains.
+const PROVIDERS: &[(&str, &str, &str)] = &[
+    ("github.com", "GitHub", "github"),
+    ("github.io", "GitHub Pages", "github"),
+    ("gitlab.com", "GitLab", "gitlab"),
+    ("gitlab.io", "GitLab Pages", "gitlab"),
+    ("codeberg.org", "Codeberg", "codeberg"),
+    ("codeberg.page", "Codeberg Pages", "codeberg"),
+    ("bitbucket.org", "Bitbucket", "bitbucket"),
+    ("sr.ht", "SourceHut", "sourcehut"),
+    ("crates.io", "crates.io", "rust"),
+    ("docs.rs", "docs.rs", "docsrs"),
+    ("npmjs.com", "npm", "npm"),
+    ("pypi.org", "PyPI", "python"),
+    ("itch.io", "itch.io", "itchio"),
+    ("store.steampowered.com", "Steam", "steam"),
+    ("play.google.com", "Google Play", "googleplay"),
+    ("apps.apple.com", "App Store", "appstore"),
+    ("youtube.com", "YouTube", "youtube"),
+    ("youtu.be", "YouTube", "youtube"),
+    ("vimeo.com", "Vimeo", "vimeo"),
+    ("medium.com", "Medium", "medium"),
+    ("dev.to", "DEV", "devto"),
+    ("huggingface.co", "Hugging Face", "huggingface"),
+];
+
+/// The host of `url` in lowercase, without a leading `www.`.
+pub(crate) fn host(url: &Url) -> Option<String> {
+    let host = url.host_str()?.to_lowercase();
+    Some(host.strip_prefix("www.").unwrap_or(&host).to_string())
+}
+
+impl Provider {
+    pub fn classify(url: &Url) -> Option<Provider> {
+        let host = host(url)?;
+        PROVIDERS
+            .iter()
+            .find(|(domain, ..)| {
+                host == *domain
+                    || host
+                        .strip_suffix(domain)
+                        .is_some_and(|sub| sub.ends_with('.'))
+            })
+            .map(|&(_, name, icon)| Provider { name, icon })
+    }
+}
+
+/// A display name for a link to `url` that has none: the provider's name, or else the host.
+pub fn suggest_name(url: &Url) -> Option<String> {
+    match Provider::classify(url) {
+        Some(provider) => Some(provider.name.to_string()),
+        None => host(url).filter(|host| !host.is_empty()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(url: &str) -> Option<(&'static str, &'static str)> {
+        Provider::classify(&Url::parse(url).unwrap()).map(|provider| (provider.name, provider.icon))
+    }
+
+    #[test]
+    fn known_hosts() {
+        assert_eq!(
+            classify("https://github.com/itscrystalline/blogger"),
+            Some(("GitHub", "github"))
+        );
+        assert_eq!(
+            classify("https://WWW.YouTube.com/watch?v=1"),
+            Some(("YouTube", "youtube"))
+        );
+        assert_eq!(
+            classify("https://someone.itch.io/game"),
+            Some(("itch.io", "itchio"))
+        );
+        assert_eq!(
+            classify("https://git.sr.ht/~someone/repo"),
+            Some(("SourceHut", "sourcehut"))
+        );
+        assert_eq!(classify("https://notgithub.com"), None);
+        assert_eq!(classify("https://example.com"), None);
+    }
+    #[test]
+    fn suggested_names() {
+        let suggest = |url: &str| suggest_name(&Url::parse(url).unwrap());
+        assert_eq!(
+            suggest("https://crates.io/crates/serde").as_deref(),
+            Some("crates.io")
+        );
+        assert_eq!(
+            suggest("https://www.example.com/a").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(suggest("mailto:someone@example.com"), None);
+    }
+}
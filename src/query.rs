@@ -0,0 +1,415 @@
+//! A small boolean query language for filtering projects, e.g.
+//! `tag:rust AND (tag:wasm OR title:"game") AND NOT tag:java`.
+//!
+//! ```text
+//! query   = or
+//! or      = and ("OR" and)*
+//! and     = unary ("AND"? unary)*
+//! unary   = ("NOT" | "-") unary | primary
+//! primary = "(" or ")" | field ":" value | value
+//! field   = "tag" | "title" | "description" | "link" | "text"
+//! value   = word | '"' quoted '"'
+//! ```
+//!
+//! A bare value searches the title and description. Tags match case-insensitively and include
+//! tags under them in the [`TagRegistry`]; everything else matches substrings.
+use std::fmt;
+
+use rocket_db_pools::sqlx::{MySql, QueryBuilder};
+
+use crate::{Project, QueryError, db::push_list, filter::like, tags::TagRegistry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Tag,
+    Title,
+    Description,
+    Link,
+    /// Title or description.
+    Text,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    Term { field: Field, value: String },
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Colon,
+    Word(String),
+    Quoted(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Open => f.write_str("\"(\""),
+            Token::Close => f.write_str("\")\""),
+            Token::And => f.write_str("AND"),
+            Token::Or => f.write_str("OR"),
+            Token::Not => f.write_str("NOT"),
+            Token::Colon => f.write_str("\":\""),
+            Token::Word(word) => write!(f, "{word:?}"),
+            Token::Quoted(text) => write!(f, "\"{text}\""),
+        }
+    }
+}
+
+/// Splits `source` into tokens along with the character position each starts at.
+fn lex(source: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let mut tokens = vec![];
+    let mut chars = source.chars().enumerate().peekable();
+    while let Some((position, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ':' => Token::Colon,
+            '-' => Token::Not,
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c)) => text.push(c),
+                            None => return Err(QueryError::UnterminatedString(position)),
+                        },
+                        Some((_, c)) => text.push(c),
+                        None => return Err(QueryError::UnterminatedString(position)),
+                    }
+                }
+                Token::Quoted(text)
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some((_, c)) =
+                    chars.next_if(|(_, c)| !c.is_whitespace() && !"():\"".contains(*c))
+                {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Word(word),
+                }
+            }
+        };
+        tokens.push((position, token));
+    }
+    Ok(tokens)
+}
+
+/// How deeply parentheses and `NOT`s may nest, so a hostile query cannot exhaust the stack.
+const MAX_DEPTH: usize = 32;
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    at: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at).map(|(_, token)| token)
+    }
+    fn next(&mut self) -> Result<(usize, Token), QueryError> {
+        let token = self.tokens.get(self.at).cloned().ok_or(QueryError::End)?;
+        self.at += 1;
+        Ok(token)
+    }
+
+    fn or(&mut self) -> Result<Query, QueryError> {
+        let mut query = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.at += 1;
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+    fn and(&mut self) -> Result<Query, QueryError> {
+        let mut query = self.unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => self.at += 1,
+                Some(Token::Or | Token::Close) | None => return Ok(query),
+                Some(_) => {}
+            }
+            query = Query::And(Box::new(query), Box::new(self.unary()?));
+        }
+    }
+    /// Runs `parse` one level deeper, for the operator at `position`.
+    fn nested(
+        &mut self,
+        position: usize,
+        parse: fn(&mut Self) -> Result<Query, QueryError>,
+    ) -> Result<Query, QueryError> {
+        if self.depth == MAX_DEPTH {
+            return Err(QueryError::TooDeep(position));
+        }
+        self.depth += 1;
+        let query = parse(self);
+        self.depth -= 1;
+        query
+    }
+    fn unary(&mut self) -> Result<Query, QueryError> {
+        if let Some(&(position, Token::Not)) = self.tokens.get(self.at) {
+            self.at += 1;
+            return Ok(Query::Not(Box::new(self.nested(position, Self::unary)?)));
+        }
+        self.primary()
+    }
+    fn primary(&mut self) -> Result<Query, QueryError> {
+        match self.next()? {
+            (position, Token::Open) => {
+                let query = self.nested(position, Self::or)?;
+                match self.next()? {
+                    (_, Token::Close) => Ok(query),
+                    (position, found) => Err(QueryError::Unexpected {
+                        found: found.to_string(),
+                        position,
+                    }),
+                }
+            }
+            (position, Token::Word(word)) if self.peek() == Some(&Token::Colon) => {
+                self.at += 1;
+                let field = match word.to_lowercase().as_str() {
+                    "tag" => Field::Tag,
+                    "title" => Field::Title,
+                    "description" | "desc" => Field::Description,
+                    "link" => Field::Link,
+                    "text" => Field::Text,
+                    _ => {
+                        return Err(QueryError::UnknownField {
+                            field: word,
+                            position,
+                        });
+                    }
+                };
+                match self.next()? {
+                    (_, Token::Word(value) | Token::Quoted(value)) => {
+                        Ok(Query::Term { field, value })
+                    }
+                    (position, found) => Err(QueryError::Unexpected {
+                        found: found.to_string(),
+                        position,
+                    }),
+                }
+            }
+            (_, Token::Word(value) | Token::Quoted(value)) => Ok(Query::Term {
+                field: Field::Text,
+                value,
+            }),
+            (position, found) => Err(QueryError::Unexpected {
+                found: found.to_string(),
+                position,
+            }),
+        }
+    }
+}
+
+fn contains(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+impl Query {
+    pub fn parse(source: &str) -> Result<Query, QueryError> {
+        let mut parser = Parser {
+            tokens: lex(source)?,
+            at: 0,
+            depth: 0,
+        };
+        let query = parser.or()?;
+        match parser.tokens.get(parser.at) {
+            None => Ok(query),
+            Some((position, found)) => Err(QueryError::Unexpected {
+                found: found.to_string(),
+                position: *position,
+            }),
+        }
+    }
+
+    pub fn matches(&self, project: &Project, registry: &TagRegistry) -> bool {
+        match self {
+            Query::Term { field, value } => match field {
+                Field::Tag => registry.matches(project, value),
+                Field::Title => contains(&project.title, value),
+                Field::Description => contains(&project.description, value),
+                Field::Link => project.links.iter().any(|link| contains(&link.name, value)),
+                Field::Text => {
+                    contains(&project.title, value) || contains(&project.description, value)
+                }
+            },
+            Query::And(a, b) => a.matches(project, registry) && b.matches(project, registry),
+            Query::Or(a, b) => a.matches(project, registry) || b.matches(project, registry),
+            Query::Not(query) => !query.matches(project, registry),
+        }
+    }
+
+    /// Appends the query as a parameterized condition on `projects`.
+    pub fn push_sql(&self, sql: &mut QueryBuilder<'_, MySql>, registry: &TagRegistry) {
+        match self {
+            Query::Term { field, value } => match field {
+                Field::Tag => {
+                    let mut tags = registry.descendants(value).into_iter().collect::<Vec<_>>();
+                    tags.sort();
+                    sql.push(
+                        "EXISTS (SELECT 1 FROM project_tags WHERE project_tags.project_id = projects.id \
+                         AND LOWER(TRIM(project_tags.tag)) IN ",
+                    );
+                    push_list(sql, tags).push(")");
+                }
+                Field::Title => _ = sql.push("title LIKE ").push_bind(like(value)),
+                Field::Description => _ = sql.push("description LIKE ").push_bind(like(value)),
+                Field::Link => {
+                    sql.push(
+                        "EXISTS (SELECT 1 FROM project_links WHERE project_links.project_id = projects.id \
+                         AND project_links.name LIKE ",
+                    )
+                    .push_bind(like(value))
+                    .push(")");
+                }
+                Field::Text => {
+                    sql.push("(title LIKE ")
+                        .push_bind(like(value))
+                        .push(" OR description LIKE ")
+                        .push_bind(like(value))
+                        .push(")");
+                }
+            },
+            Query::And(a, b) | Query::Or(a, b) => {
+                sql.push("(");
+                a.push_sql(sql, registry);
+                sql.push(if matches!(self, Query::And(..)) {
+                    " AND "
+                } else {
+                    " OR "
+                });
+                b.push_sql(sql, registry);
+                sql.push(")");
+            }
+            Query::Not(query) => {
+                sql.push("NOT ");
+                query.push_sql(sql, registry);
+            }
+        }
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Query::Term { field, value } => {
+                let field = match field {
+                    Field::Tag => "tag",
+                    Field::Title => "title",
+                    Field::Description => "description",
+                    Field::Link => "link",
+                    Field::Text => "text",
+                };
+                write!(f, "{field}:{value:?}")
+            }
+            Query::And(a, b) => write!(f, "({a} AND {b})"),
+            Query::Or(a, b) => write!(f, "({a} OR {b})"),
+            Query::Not(query) => write!(f, "NOT {query}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builders::ProjectBuilder;
+
+    const EXAMPLE: &str = r#"tag:rust AND (tag:wasm OR title:"game") AND NOT tag:java"#;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Query::parse(EXAMPLE).unwrap().to_string(),
+            r#"((tag:"rust" AND (tag:"wasm" OR title:"game")) AND NOT tag:"java")"#
+        );
+        assert_eq!(
+            Query::parse("blog -tag:java OR link:docs")
+                .unwrap()
+                .to_string(),
+            r#"((text:"blog" AND NOT tag:"java") OR link:"docs")"#
+        );
+    }
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Query::parse("tag:rust AND"), Err(QueryError::End));
+        assert_eq!(Query::parse("(tag:rust"), Err(QueryError::End));
+        assert_eq!(
+            Query::parse("tag:rust)"),
+            Err(QueryError::Unexpected {
+                found: "\")\"".to_string(),
+                position: 8
+            })
+        );
+        assert_eq!(
+            Query::parse("colour:red"),
+            Err(QueryError::UnknownField {
+                field: "colour".to_string(),
+                position: 0
+            })
+        );
+        assert_eq!(
+            Query::parse("title:\"game"),
+            Err(QueryError::UnterminatedString(6))
+        );
+    }
+    #[test]
+    fn nesting_limit() {
+        let nested = |depth: usize| format!("{}tag:rust{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(Query::parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            Query::parse(&nested(MAX_DEPTH + 1)),
+            Err(QueryError::TooDeep(MAX_DEPTH))
+        );
+        assert_eq!(
+            Query::parse(&"NOT ".repeat(100_000)),
+            Err(QueryError::TooDeep(4 * MAX_DEPTH))
+        );
+    }
+    #[test]
+    fn evaluate() {
+        let query = Query::parse(EXAMPLE).unwrap();
+        let registry = TagRegistry::default();
+        let matches = |title: &str, tags: &[&str]| {
+            let project = ProjectBuilder::sample(tags).title(title).bulid().unwrap();
+            query.matches(&project, &registry)
+        };
+        assert!(matches("Bevy Game", &["Rust"]));
+        assert!(matches("Renderer", &["rust", "WASM"]));
+        assert!(!matches("Renderer", &["Rust"]));
+        assert!(!matches("Game", &["Rust", "Java"]));
+    }
+    #[test]
+    fn compile() {
+        let mut sql = QueryBuilder::new("SELECT * FROM projects WHERE ");
+        Query::parse(EXAMPLE)
+            .unwrap()
+            .push_sql(&mut sql, &TagRegistry::default());
+        assert_eq!(
+            sql.sql(),
+            "SELECT * FROM projects WHERE \
+             ((EXISTS (SELECT 1 FROM project_tags WHERE project_tags.project_id = projects.id \
+             AND LOWER(TRIM(project_tags.tag)) IN (?)) \
+             AND (EXISTS (SELECT 1 FROM project_tags WHERE project_tags.project_id = projects.id \
+             AND LOWER(TRIM(project_tags.tag)) IN (?)) OR title LIKE ?)) \
+             AND NOT EXISTS (SELECT 1 FROM project_tags WHERE project_tags.project_id = projects.id \
+             AND LOWER(TRIM(project_tags.tag)) IN (?)))"
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! Keeps the in-memory tag registry, search index and related projects in step with writes made
+//! outside the server, such as by the CLI.
+use std::time::Duration;
+
+use rocket::{
+    Orbit, Rocket,
+    fairing::{Fairing, Info, Kind},
+    figment::Figment,
+    tokio::{self, time::Instant},
+};
+use rocket_db_pools::{
+    Database,
+    sqlx::{self, MySqlConnection, Result, Row, mysql::MySqlRow},
+};
+use serde::Deserialize;
+
+use crate::{db::BloggerDatabase, related, search, tags::TagRegistry};
+
+/// Changes whenever a project, its order or trash state, or the tag registry does. Every write to
+/// a project's content records a revision, so counting those covers tags and links too.
+const FINGERPRINT: &str = "SELECT CAST(CONCAT_WS(':', \
+     (SELECT COUNT(*) FROM project_revisions), \
+     (SELECT BIT_XOR(CRC32(CONCAT_WS('|', id, featured, position, deleted_at))) FROM projects), \
+     (SELECT BIT_XOR(CRC32(CONCAT_WS('|', id, name, color, icon, description, parent_id))) FROM tags), \
+     (SELECT BIT_XOR(CRC32(CONCAT_WS('|', alias, tag_id))) FROM tag_aliases)) AS CHAR) AS fingerprint";
+
+pub async fn fingerprint(db: &mut MySqlConnection) -> Result<String> {
+    sqlx::query(FINGERPRINT)
+        .map(|row: MySqlRow| row.get("fingerprint"))
+        .fetch_one(db)
+        .await
+}
+
+/// Reloads everything the server keeps in memory.
+pub async fn reload(db: &mut MySqlConnection) {
+    if let Err(e) = TagRegistry::reload(db).await {
+        log::error!("cannot load the tag registry: {e}");
+    }
+    search::reindex(db).await;
+    related::recompute(db).await;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct RefreshConfig {
+    /// How often to look for outside writes. 0 turns the check off.
+    pub interval_secs: u64,
+}
+impl Default for RefreshConfig {
+    fn default() -> Self {
+        RefreshConfig { interval_secs: 30 }
+    }
+}
+impl RefreshConfig {
+    /// Reads `[refresh]`, falling back to the defaults if the section is missing.
+    pub fn from_figment(figment: &Figment) -> Result<Self, Box<rocket::figment::Error>> {
+        if figment.contains("refresh") {
+            figment.extract_inner("refresh").map_err(Box::new)
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+/// Reloads the in-memory state when the database changed, checking on the interval set under
+/// `[refresh]` in `Rocket.toml`.
+pub struct Refresher;
+
+#[rocket::async_trait]
+impl Fairing for Refresher {
+    fn info(&self) -> Info {
+        Info {
+            name: "Refresher",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let Some(pool) = BloggerDatabase::fetch(rocket).map(|db| (**db).clone()) else {
+            return;
+        };
+        let config = match RefreshConfig::from_figment(rocket.figment()) {
+            Ok(config) => config,
+            Err(e) => return log::error!("invalid [refresh] config, not refreshing: {e}"),
+        };
+        if config.interval_secs == 0 {
+            return;
+        }
+        let period = Duration::from_secs(config.interval_secs);
+        let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+        tokio::spawn(async move {
+            let mut last = None;
+            loop {
+                let mut db = match pool.acquire().await {
+                    Ok(db) => db,
+                    Err(e) => {
+                        log::error!("cannot check for outside writes: {e}");
+                        interval.tick().await;
+                        continue;
+                    }
+                };
+                match fingerprint(&mut db).await {
+                    // The loaders already ran at liftoff, so the first fingerprint is current.
+                    Ok(current) if last.is_none() => last = Some(current),
+                    Ok(current) if last.as_ref() != Some(&current) => {
+                        log::info!("the database changed, reloading");
+                        reload(&mut db).await;
+                        last = Some(current);
+                    }
+                    Ok(_) => {}
+                    Err(e) => log::error!("cannot check for outside writes: {e}"),
+                }
+                drop(db);
+                interval.tick().await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::figment::providers::{Format, Toml};
+
+    use super::*;
+
+    #[test]
+    fn config() {
+        let config = |toml: &str| RefreshConfig::from_figment(&Figment::from(Toml::string(toml)));
+        assert_eq!(
+            config("[refresh]\ninterval_secs = 5")
+                .unwrap()
+                .interval_secs,
+            5
+        );
+        assert_eq!(config("").unwrap(), RefreshConfig::default());
+        assert!(config("[refresh]\ninterval_secs = -1").is_err());
+    }
+}
@@ -0,0 +1,315 @@
+//! "You might also like" recommendations between projects.
+//!
+//! Projects are compared by the tags they share, with rare tags counting more, by the hosts their
+//! links point to and by the TF-IDF cosine similarity of their descriptions. Recommendations for
+//! every project are precomputed after each write, like the search index.
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use rocket::{
+    Orbit, Rocket,
+    fairing::{Fairing, Info, Kind},
+};
+use rocket_db_pools::{
+    Database,
+    sqlx::{MySqlConnection, Result},
+};
+use serde::Serialize;
+
+use crate::{Project, Projects, db::BloggerDatabase, providers::host, search::terms};
+
+static RELATED: LazyLock<RwLock<Arc<Related>>> = LazyLock::new(RwLock::default);
+
+pub const DEFAULT_LIMIT: usize = 5;
+/// Recommendations kept for each project, and the most the API returns.
+pub const MAX_LIMIT: usize = 20;
+const TAG_WEIGHT: f32 = 0.6;
+const HOST_WEIGHT: f32 = 0.2;
+const TEXT_WEIGHT: f32 = 0.2;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RelatedProject {
+    pub project: Project,
+    /// Similarity between 0 and 1.
+    pub score: f32,
+    /// Tags both projects have, as spelled on `project`.
+    pub shared_tags: Vec<String>,
+}
+
+/// What a project is compared on.
+struct Features {
+    id: u32,
+    tags: HashSet<String>,
+    hosts: HashSet<String>,
+    /// TF-IDF weights of the description's terms, scaled to unit length.
+    text: HashMap<String, f32>,
+}
+
+fn idf(frequency: usize, total: usize) -> f32 {
+    ((1 + total) as f32 / (1 + frequency) as f32).ln() + 1.0
+}
+
+/// Weighted Jaccard similarity: the weight of the shared items over the weight of all of them.
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>, weight: impl Fn(&str) -> f32) -> f32 {
+    let union = a.union(b).map(|item| weight(item)).sum::<f32>();
+    if union == 0.0 {
+        return 0.0;
+    }
+    a.intersection(b).map(|item| weight(item)).sum::<f32>() / union
+}
+
+fn cosine(a: &HashMap<String, f32>, b: &HashMap<String, f32>) -> f32 {
+    a.iter()
+        .filter_map(|(term, weight)| b.get(term).map(|other| weight * other))
+        .sum()
+}
+
+#[derive(Debug, Default)]
+pub struct Related {
+    projects: HashMap<u32, Project>,
+    /// Project id to its most similar projects, best first.
+    ranked: HashMap<u32, Vec<(u32, f32)>>,
+}
+
+impl Related {
+    pub fn new(projects: impl IntoIterator<Item = Project>) -> Self {
+        let projects = projects
+            .into_iter()
+            .filter_map(|project| Some((project.id?, project)))
+            .collect::<HashMap<_, _>>();
+        let mut ids = projects.keys().copied().collect::<Vec<_>>();
+        ids.sort();
+
+        let mut tag_frequencies: HashMap<String, usize> = HashMap::new();
+        let mut term_frequencies: HashMap<String, usize> = HashMap::new();
+        let mut counts = vec![];
+        for id in &ids {
+            let project = &projects[id];
+            let tags = project
+                .tags
+                .iter()
+                .map(|tag| tag.to_lowercase())
+                .collect::<HashSet<_>>();
+            let mut terms_count: HashMap<String, f32> = HashMap::new();
+            for term in terms(&project.description) {
+                *terms_count.entry(term).or_default() += 1.0;
+            }
+            for tag in &tags {
+                *tag_frequencies.entry(tag.clone()).or_default() += 1;
+            }
+            for term in terms_count.keys() {
+                *term_frequencies.entry(term.clone()).or_default() += 1;
+            }
+            counts.push((tags, terms_count));
+        }
+
+        let total = ids.len();
+        let features = ids
+            .iter()
+            .zip(counts)
+            .map(|(&id, (tags, mut text))| {
+                for (term, weight) in text.iter_mut() {
+                    *weight *= idf(term_frequencies[term], total);
+                }
+                let norm = text
+                    .values()
+                    .map(|weight| weight * weight)
+                    .sum::<f32>()
+                    .sqrt();
+                if norm > 0.0 {
+                    text.values_mut().for_each(|weight| *weight /= norm);
+                }
+                Features {
+                    id,
+                    tags,
+                    hosts: projects[&id]
+                        .links
+                        .iter()
+                        .filter_map(|link| host(&link.link))
+                        .collect(),
+                    text,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let tag_weight = |tag: &str| idf(tag_frequencies[tag], total);
+        let mut ranked: HashMap<u32, Vec<(u32, f32)>> = HashMap::new();
+        for (i, a) in features.iter().enumerate() {
+            for b in &features[i + 1..] {
+                let score = TAG_WEIGHT * jaccard(&a.tags, &b.tags, tag_weight)
+                    + HOST_WEIGHT * jaccard(&a.hosts, &b.hosts, |_| 1.0)
+                    + TEXT_WEIGHT * cosine(&a.text, &b.text);
+                if score > 0.0 {
+                    ranked.entry(a.id).or_default().push((b.id, score));
+                    ranked.entry(b.id).or_default().push((a.id, score));
+                }
+            }
+        }
+        for list in ranked.values_mut() {
+            list.sort_by(|(a, a_score), (b, b_score)| {
+                b_score
+                    .partial_cmp(a_score)
+                    .unwrap_or(Ordering::Equal)
+                    .then(a.cmp(b))
+            });
+            list.truncate(MAX_LIMIT);
+        }
+        Related { projects, ranked }
+    }
+
+    /// Up to `limit` projects most like project `id`, or `None` if there is no such project.
+    pub fn related(&self, id: u32, limit: usize) -> Option<Vec<RelatedProject>> {
+        let project = self.projects.get(&id)?;
+        let tags = project
+            .tags
+            .iter()
+            .map(|tag| tag.to_lowercase())
+            .collect::<HashSet<_>>();
+        let ranked = self.ranked.get(&id).map(Vec::as_slice).unwrap_or_default();
+        Some(
+            ranked
+                .iter()
+                .take(limit)
+                .map(|(other, score)| {
+                    let project = self.projects[other].clone();
+                    RelatedProject {
+                        shared_tags: project
+                            .tags
+                            .iter()
+                            .filter(|tag| tags.contains(&tag.to_lowercase()))
+                            .cloned()
+                            .collect(),
+                        project,
+                        score: *score,
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    pub fn global() -> Arc<Related> {
+        RELATED.read().unwrap().clone()
+    }
+    pub fn install(self) {
+        *RELATED.write().unwrap() = Arc::new(self);
+    }
+
+    /// Recomputes the global recommendations from the live projects in the database.
+    pub async fn rebuild(db: &mut MySqlConnection) -> Result<()> {
+        let projects = Projects::get(db).await?;
+        Related::new(projects.projects).install();
+        Ok(())
+    }
+}
+
+/// Recomputes the recommendations after a write, logging instead of failing if it cannot.
+pub async fn recompute(db: &mut MySqlConnection) {
+    if let Err(e) = Related::rebuild(db).await {
+        log::error!("cannot compute related projects: {e}");
+    }
+}
+
+/// Computes the recommendations once the database is available.
+pub struct Recommender;
+
+#[rocket::async_trait]
+impl Fairing for Recommender {
+    fn info(&self) -> Info {
+        Info {
+            name: "Related Projects",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let Some(pool) = BloggerDatabase::fetch(rocket) else {
+            return;
+        };
+        match pool.acquire().await {
+            Ok(mut db) => recompute(&mut db).await,
+            Err(e) => log::error!("cannot compute related projects: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builders::{LinkBuilder, ProjectBuilder};
+    use url::Url;
+
+    fn related() -> Related {
+        Related::new(
+            [
+                (
+                    1,
+                    "A chess engine",
+                    &["Rust", "Games"][..],
+                    "https://github.com/a",
+                ),
+                (
+                    2,
+                    "A chess engine in the browser",
+                    &["Rust", "WASM", "Games"],
+                    "https://github.com/b",
+                ),
+                (
+                    3,
+                    "A puzzle game",
+                    &["Java", "Games"],
+                    "https://gitlab.com/c",
+                ),
+                (4, "Notes on cooking", &["Food"], "https://example.com/d"),
+            ]
+            .map(|(id, description, tags, link)| {
+                let link = LinkBuilder::new()
+                    .name("Source")
+                    .url(Url::parse(link).unwrap())
+                    .bulid()
+                    .unwrap();
+                ProjectBuilder::sample(tags)
+                    .id(id)
+                    .description(description)
+                    .remove_link("Example")
+                    .add_link(link)
+                    .bulid()
+                    .unwrap()
+            }),
+        )
+    }
+
+    #[test]
+    fn ranking() {
+        let related = related();
+        let first = related.related(1, DEFAULT_LIMIT).unwrap();
+        let ids = first
+            .iter()
+            .map(|related| related.project.id.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [2, 3]);
+        assert_eq!(first[0].shared_tags, ["Rust", "Games"]);
+        assert!(first[0].score > first[1].score);
+        assert!(first[0].score <= 1.0);
+
+        assert_eq!(related.related(1, 1).unwrap().len(), 1);
+        assert!(related.related(4, DEFAULT_LIMIT).unwrap().is_empty());
+        assert!(related.related(5, DEFAULT_LIMIT).is_none());
+    }
+    #[test]
+    fn symmetric() {
+        let related = related();
+        let score = |a, b| {
+            related
+                .related(a, MAX_LIMIT)
+                .unwrap()
+                .into_iter()
+                .find(|related| related.project.id == Some(b))
+                .unwrap()
+                .score
+        };
+        assert_eq!(score(1, 3), score(3, 1));
+    }
+}
@@ -0,0 +1,145 @@
+//! Immutable history of every write to a project.
+use rocket_db_pools::sqlx::{self, MySqlConnection, Result, Row, mysql::MySqlRow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Project,
+    builders::{Edit, ProjectBuilder},
+    validation::ValidationPolicy,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevisionSummary {
+    pub revision: u32,
+    pub author: String,
+    /// Unix timestamp in seconds.
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revision {
+    pub project_id: u32,
+    #[serde(flatten)]
+    pub summary: RevisionSummary,
+    pub snapshot: Project,
+}
+
+fn summary(row: &MySqlRow) -> RevisionSummary {
+    RevisionSummary {
+        revision: row.get("revision"),
+        author: row.get("author"),
+        created_at: row.get("created_at"),
+    }
+}
+
+impl Revision {
+    /// Stores `project` as the next revision of project `id` and returns its number. Meant to run
+    /// inside the transaction of the write it records.
+    pub async fn record(
+        id: u32,
+        project: &Project,
+        author: &str,
+        db: &mut MySqlConnection,
+    ) -> Result<u32> {
+        let mut snapshot = project.clone();
+        snapshot.id = Some(id);
+        let snapshot = serde_json::to_string(&snapshot).expect("projects always serialize");
+        let latest: Option<u32> = sqlx::query(
+            "SELECT revision FROM project_revisions WHERE project_id = ? ORDER BY revision DESC LIMIT 1 FOR UPDATE",
+        )
+        .bind(id)
+        .fetch_optional(&mut *db)
+        .await?
+        .map(|row| row.get("revision"));
+        let revision = latest.unwrap_or(0) + 1;
+        sqlx::query(
+            "INSERT INTO project_revisions (project_id, revision, author, snapshot) VALUES (?, ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(revision)
+        .bind(author)
+        .bind(snapshot)
+        .execute(&mut *db)
+        .await?;
+        Ok(revision)
+    }
+
+    /// Lists the revisions of project `id`, newest first.
+    pub async fn list(id: u32, db: &mut MySqlConnection) -> Result<Vec<RevisionSummary>> {
+        sqlx::query(
+            "SELECT revision, author, CAST(UNIX_TIMESTAMP(created_at) AS SIGNED) AS created_at \
+             FROM project_revisions WHERE project_id = ? ORDER BY revision DESC",
+        )
+        .bind(id)
+        .map(|row: MySqlRow| summary(&row))
+        .fetch_all(db)
+        .await
+    }
+
+    pub async fn get(id: u32, revision: u32, db: &mut MySqlConnection) -> Result<Option<Revision>> {
+        let Some(row) = sqlx::query(
+            "SELECT revision, author, CAST(UNIX_TIMESTAMP(created_at) AS SIGNED) AS created_at, snapshot \
+             FROM project_revisions WHERE project_id = ? AND revision = ?",
+        )
+        .bind(id)
+        .bind(revision)
+        .fetch_optional(db)
+        .await?
+        else {
+            return Ok(None);
+        };
+        // Rules may have tightened since the snapshot was taken.
+        let snapshot = serde_json::from_str::<ProjectBuilder>(row.get("snapshot"))
+            .map_err(|e| sqlx::Error::Decode(e.into()))?
+            .bulid_with(ValidationPolicy::permissive())
+            .map_err(|e| sqlx::Error::Decode(e.into()))?;
+        Ok(Some(Revision {
+            project_id: id,
+            summary: summary(&row),
+            snapshot,
+        }))
+    }
+
+    /// Turns the snapshot back into a builder so it can be saved as the newest version.
+    pub fn restore(self) -> ProjectBuilder {
+        self.snapshot.edit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builders::LinkBuilder;
+
+    #[test]
+    fn restore_revision() {
+        let snapshot = ProjectBuilder::new()
+            .id(3)
+            .title("old title")
+            .description("old description")
+            .add_tag("Rust")
+            .add_link(LinkBuilder::sample())
+            .bulid()
+            .unwrap();
+        let revision = Revision {
+            project_id: 3,
+            summary: RevisionSummary {
+                revision: 2,
+                author: "crystal".to_string(),
+                created_at: 1_700_000_000,
+            },
+            snapshot: snapshot.clone(),
+        };
+        let json = serde_json::to_value(&revision).unwrap();
+        assert_eq!(json["revision"], 2);
+        assert_eq!(json["author"], "crystal");
+        assert_eq!(json["snapshot"]["title"], "old title");
+
+        let mut builder = revision.restore();
+        builder.add_tag("Rocket");
+        let restored = builder.bulid().unwrap();
+        assert_eq!(restored.id, Some(3));
+        assert_eq!(restored.title, snapshot.title);
+        assert_eq!(restored.tags, vec!["Rust", "Rocket"]);
+    }
+}
@@ -0,0 +1,443 @@
+//! Bulk rewriting of link and cover URLs, e.g. after a project host moves.
+//!
+//! A rewrite is a list of rules tried in order; the first one matching a URL rewrites it. The
+//! result is canonicalized and must pass the validation policy like any other link, and a rewrite
+//! that would leave a project invalid, e.g. with two identical links, is refused.
+use std::fmt;
+
+use rocket_db_pools::sqlx::{self, Connection, MySqlConnection, Row, mysql::MySqlRow};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    Project, RewriteError, builders::Edit, db::load, revisions::Revision,
+    validation::ValidationPolicy,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "match", rename_all = "lowercase")]
+pub enum RewriteRule {
+    /// Moves URLs on host `from` to host `to`, keeping the rest of the URL. Hosts compare
+    /// case-insensitively and subdomains do not match.
+    Host { from: String, to: String },
+    /// Replaces the leading `from` of URLs that start with it with `to`. `from` is a URL and only
+    /// matches whole hosts and path segments, so `https://github.com/a` matches
+    /// `https://github.com/a/b` but not `https://github.com/ab` or `https://github.com.evil.org`.
+    Prefix { from: String, to: String },
+}
+
+impl RewriteRule {
+    fn check(&self) -> Result<(), RewriteError> {
+        match self {
+            RewriteRule::Host { from, to } => {
+                let to_ok = !to.is_empty()
+                    && Url::parse(&format!("http://{to}/"))
+                        .is_ok_and(|url| url.host_str() == Some(to.to_lowercase().as_str()));
+                if from.is_empty() || !to_ok {
+                    return Err(RewriteError::Rule(self.to_string()));
+                }
+            }
+            RewriteRule::Prefix { from, to } => {
+                if Url::parse(from).is_err() || Url::parse(to).is_err() {
+                    return Err(RewriteError::Rule(self.to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `url` rewritten, or `None` if the rule does not match it.
+    pub fn rewrite(&self, url: &str) -> Result<Option<Url>, RewriteError> {
+        let invalid = |to: String| RewriteError::InvalidUrl {
+            from: url.to_string(),
+            to,
+        };
+        match self {
+            RewriteRule::Host { from, to } => {
+                let Ok(mut rewritten) = Url::parse(url) else {
+                    return Ok(None);
+                };
+                if !rewritten
+                    .host_str()
+                    .is_some_and(|host| host.eq_ignore_ascii_case(from))
+                {
+                    return Ok(None);
+                }
+                rewritten
+                    .set_host(Some(to))
+                    .map_err(|_| invalid(to.clone()))?;
+                Ok(Some(rewritten))
+            }
+            RewriteRule::Prefix { from, to } => {
+                // Compared in the form URLs are stored in, e.g. with the root path's `/`.
+                let Ok(from) = Url::parse(from) else {
+                    return Ok(None);
+                };
+                let from = from.as_str();
+                let Some(rest) = url.strip_prefix(from).filter(|rest| {
+                    from.ends_with('/') || rest.is_empty() || rest.starts_with(['/', '?', '#'])
+                }) else {
+                    return Ok(None);
+                };
+                let to = match (from.ends_with('/'), to.ends_with('/')) {
+                    (true, false) => format!("{to}/"),
+                    (false, true) if rest.starts_with('/') => to[..to.len() - 1].to_string(),
+                    _ => to.clone(),
+                };
+                let rewritten = format!("{to}{rest}");
+                Url::parse(&rewritten)
+                    .map(Some)
+                    .map_err(|_| invalid(rewritten))
+            }
+        }
+    }
+}
+
+impl fmt::Display for RewriteRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RewriteRule::Host { from, to } => write!(f, "host {from:?} to {to:?}"),
+            RewriteRule::Prefix { from, to } => write!(f, "prefix {from:?} to {to:?}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkRewrite {
+    pub rules: Vec<RewriteRule>,
+}
+
+/// One URL that is, or would be, rewritten.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UrlChange {
+    pub project: u32,
+    /// The link's name, or `None` for the cover.
+    pub link: Option<String>,
+    pub from: String,
+    pub to: Url,
+}
+
+impl fmt::Display for UrlChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.link {
+            Some(name) => writeln!(f, "#{} link {name:?}", self.project)?,
+            None => writeln!(f, "#{} cover", self.project)?,
+        }
+        writeln!(f, "- {}", self.from)?;
+        writeln!(f, "+ {}", self.to)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RewriteReport {
+    pub changes: Vec<UrlChange>,
+    pub projects: Vec<u32>,
+    pub dry_run: bool,
+}
+
+impl fmt::Display for RewriteReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            write!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks a rewritten project, which was loaded as permissively as any stored one, against
+/// `policy`.
+fn revalidate(
+    id: u32,
+    project: Project,
+    policy: &ValidationPolicy,
+) -> Result<Project, RewriteError> {
+    project
+        .edit()
+        .bulid_with(policy)
+        .map_err(|_| RewriteError::Invalid(id))
+}
+
+impl LinkRewrite {
+    /// `url` rewritten by the first matching rule, or `None` if no rule changes it.
+    pub fn rewrite(
+        &self,
+        url: &str,
+        policy: &ValidationPolicy,
+    ) -> Result<Option<Url>, RewriteError> {
+        for rule in &self.rules {
+            let Some(rewritten) = rule.rewrite(url)? else {
+                continue;
+            };
+            let rewritten = policy.canonicalize(&rewritten);
+            if !policy.scheme_allowed(&rewritten) || !policy.host_allowed(&rewritten) {
+                return Err(RewriteError::NotAllowed(rewritten.to_string()));
+            }
+            return Ok((rewritten.as_str() != url).then_some(rewritten));
+        }
+        Ok(None)
+    }
+
+    /// Rewrites the covers and links of every project, trashed ones included, in one transaction,
+    /// recording a revision for each live project it changes. With `dry_run` the transaction is
+    /// rolled back, so the report shows what would happen.
+    pub async fn apply(
+        &self,
+        author: &str,
+        dry_run: bool,
+        db: &mut MySqlConnection,
+    ) -> Result<RewriteReport, RewriteError> {
+        if self.rules.is_empty() {
+            return Err(RewriteError::NoRules);
+        }
+        for rule in &self.rules {
+            rule.check()?;
+        }
+        let policy = ValidationPolicy::global();
+        let mut tx = db.begin().await?;
+        let covers = sqlx::query(
+            "SELECT id, cover FROM projects WHERE cover IS NOT NULL ORDER BY id FOR UPDATE",
+        )
+        .map(|row: MySqlRow| (row.get::<u32, _>("id"), row.get::<String, _>("cover")))
+        .fetch_all(&mut *tx)
+        .await?;
+        let links = sqlx::query(
+            "SELECT id, project_id, name, link FROM project_links ORDER BY project_id, id \
+             FOR UPDATE",
+        )
+        .map(|row: MySqlRow| {
+            (
+                row.get::<u32, _>("id"),
+                row.get::<u32, _>("project_id"),
+                row.get::<String, _>("name"),
+                row.get::<String, _>("link"),
+            )
+        })
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut changes = vec![];
+        for (id, cover) in covers {
+            if let Some(to) = self.rewrite(&cover, policy)? {
+                sqlx::query("UPDATE projects SET cover = ? WHERE id = ?")
+                    .bind(to.as_str())
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                changes.push(UrlChange {
+                    project: id,
+                    link: None,
+                    from: cover,
+                    to,
+                });
+            }
+        }
+        for (id, project, name, link) in links {
+            if let Some(to) = self.rewrite(&link, policy)? {
+                sqlx::query("UPDATE project_links SET link = ? WHERE id = ?")
+                    .bind(to.as_str())
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                changes.push(UrlChange {
+                    project,
+                    link: Some(name),
+                    from: link,
+                    to,
+                });
+            }
+        }
+        changes.sort_by_key(|change| change.project);
+
+        let mut projects = changes
+            .iter()
+            .map(|change| change.project)
+            .collect::<Vec<_>>();
+        projects.dedup();
+        for id in &projects {
+            // Trashed projects are held to the rules too, since they can be restored.
+            let (project, trashed) = match Project::get(*id, &mut tx).await? {
+                Some(project) => (Some(project), false),
+                None => (load(&mut tx, Some(*id), true).await?.pop(), true),
+            };
+            let project = project.ok_or(RewriteError::Invalid(*id))?;
+            let project = revalidate(*id, project, policy)?;
+            if !trashed {
+                Revision::record(*id, &project, author, &mut tx).await?;
+            }
+        }
+
+        if !dry_run {
+            tx.commit().await?;
+        }
+        Ok(RewriteReport {
+            changes,
+            projects,
+            dry_run,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ProjectBuilderError,
+        builders::{LinkBuilder, ProjectBuilder},
+    };
+
+    fn host(from: &str, to: &str) -> RewriteRule {
+        RewriteRule::Host {
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+    fn prefix(from: &str, to: &str) -> RewriteRule {
+        RewriteRule::Prefix {
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+
+    #[test]
+    fn rules() {
+        let rewrite = LinkRewrite {
+            rules: vec![
+                prefix(
+                    "https://github.com/old-name/",
+                    "https://codeberg.org/new-name/",
+                ),
+                host("github.com", "gitlab.com"),
+            ],
+        };
+        let policy = ValidationPolicy::default();
+        let rewritten = |url: &str| {
+            rewrite
+                .rewrite(url, &policy)
+                .unwrap()
+                .map(|url| url.to_string())
+        };
+        assert_eq!(
+            rewritten("https://github.com/old-name/repo?utm_source=x").as_deref(),
+            Some("https://codeberg.org/new-name/repo")
+        );
+        assert_eq!(
+            rewritten("https://GitHub.com/other/repo#readme").as_deref(),
+            Some("https://gitlab.com/other/repo#readme")
+        );
+        assert_eq!(rewritten("https://docs.github.com/a"), None);
+        assert_eq!(rewritten("https://example.com/github.com"), None);
+    }
+    #[test]
+    fn prefix_boundaries() {
+        let policy = ValidationPolicy::default();
+        let rewritten = |rule: RewriteRule, url: &str| {
+            rule.check().unwrap();
+            LinkRewrite { rules: vec![rule] }
+                .rewrite(url, &policy)
+                .unwrap()
+                .map(|url| url.to_string())
+        };
+        let moved = || prefix("https://github.com/a", "https://codeberg.org/b/");
+        assert_eq!(
+            rewritten(moved(), "https://github.com/a/x").as_deref(),
+            Some("https://codeberg.org/b/x")
+        );
+        assert_eq!(
+            rewritten(moved(), "https://github.com/a?tab=1").as_deref(),
+            Some("https://codeberg.org/b/?tab=1")
+        );
+        assert_eq!(rewritten(moved(), "https://github.com/ab"), None);
+
+        let host = || prefix("https://github.com", "https://gitlab.com");
+        assert_eq!(
+            rewritten(host(), "https://github.com/x").as_deref(),
+            Some("https://gitlab.com/x")
+        );
+        assert_eq!(rewritten(host(), "https://github.com.evil.org/x"), None);
+        assert_eq!(
+            rewritten(
+                prefix("https://github.co", "https://gitlab.com"),
+                "https://github.com/x"
+            ),
+            None
+        );
+    }
+    #[test]
+    fn invalid() {
+        let policy = ValidationPolicy::default();
+        let rewrite = LinkRewrite {
+            rules: vec![prefix("https://example.com/", "not a url/")],
+        };
+        assert!(matches!(
+            rewrite.rewrite("https://example.com/a", &policy),
+            Err(RewriteError::InvalidUrl { .. })
+        ));
+        let rewrite = LinkRewrite {
+            rules: vec![prefix("https://example.com/", "ftp://example.com/")],
+        };
+        assert!(matches!(
+            rewrite.rewrite("https://example.com/a", &policy),
+            Err(RewriteError::NotAllowed(_))
+        ));
+        assert!(host("github.com", "").check().is_err());
+        assert!(host("github.com", "a/b").check().is_err());
+        assert!(prefix("", "https://example.com").check().is_err());
+        assert!(prefix("https://", "ftp://").check().is_err());
+        assert!(
+            prefix("https://example.com/", "not a url/")
+                .check()
+                .is_err()
+        );
+        assert!(host("github.com", "codeberg.org").check().is_ok());
+    }
+    #[test]
+    fn duplicates_are_invalid() {
+        let policy = ValidationPolicy::default();
+        let rewrite = LinkRewrite {
+            rules: vec![host("github.com", "gitlab.com")],
+        };
+        let link = |name: &str, url: &str| {
+            let url = rewrite
+                .rewrite(url, &policy)
+                .unwrap()
+                .unwrap_or_else(|| Url::parse(url).unwrap());
+            LinkBuilder::new().name(name).url(url).bulid().unwrap()
+        };
+        // How the project loads after the rewrite, which leaves it with the same link twice.
+        let mut builder = ProjectBuilder::new();
+        builder
+            .id(3)
+            .title("Project")
+            .description("Description")
+            .add_tag("Rust")
+            .add_link(link("GitHub", "https://github.com/x"))
+            .add_link(link("GitLab", "https://gitlab.com/x"));
+        let project = builder.bulid_with(ValidationPolicy::permissive()).unwrap();
+        assert_eq!(
+            project.clone().edit().validate_with(&policy),
+            [ProjectBuilderError::DuplicateLinks]
+        );
+        assert!(matches!(
+            revalidate(3, project, &policy),
+            Err(RewriteError::Invalid(3))
+        ));
+    }
+    #[test]
+    fn diff() {
+        let change = UrlChange {
+            project: 4,
+            link: Some("Source".to_string()),
+            from: "https://github.com/a/b".to_string(),
+            to: Url::parse("https://codeberg.org/a/b").unwrap(),
+        };
+        assert_eq!(
+            change.to_string(),
+            "#4 link \"Source\"\n- https://github.com/a/b\n+ https://codeberg.org/a/b\n"
+        );
+        let json: LinkRewrite = serde_json::from_str(
+            r#"{"rules": [{"match": "host", "from": "github.com", "to": "codeberg.org"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(json.rules, [host("github.com", "codeberg.org")]);
+    }
+}
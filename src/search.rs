@@ -0,0 +1,465 @@
+//! In-process full-text search over projects.
+//!
+//! Titles, descriptions, tags and link names are tokenized and stemmed into an inverted index
+//! that is ranked with BM25. Query terms also match indexed terms a typo or two away, and hits
+//! come with `<mark>`ed titles and description snippets. The index is rebuilt from the database
+//! after every write.
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use rocket::{
+    Orbit, Rocket,
+    fairing::{Fairing, Info, Kind},
+};
+use rocket_db_pools::{
+    Database,
+    sqlx::{MySqlConnection, Result},
+};
+use serde::Serialize;
+
+use crate::{Project, Projects, db::BloggerDatabase};
+
+static INDEX: LazyLock<RwLock<Arc<SearchIndex>>> = LazyLock::new(RwLock::default);
+
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+const TITLE_WEIGHT: f32 = 3.0;
+const TAG_WEIGHT: f32 = 2.0;
+const LINK_WEIGHT: f32 = 1.0;
+const DESCRIPTION_WEIGHT: f32 = 1.0;
+/// Tokens shown before and after the first match in a snippet.
+const SNIPPET_BEFORE: usize = 8;
+const SNIPPET_AFTER: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Token<'a> {
+    text: &'a str,
+    start: usize,
+    end: usize,
+}
+
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut start = None;
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (start, c.is_alphanumeric()) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                tokens.push(Token {
+                    text: &text[s..i],
+                    start: s,
+                    end: i,
+                });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+/// Stemmed terms of `text`, as they are indexed.
+pub(crate) fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    tokenize(text).into_iter().map(|token| stem(token.text))
+}
+
+fn has_vowel(word: &str) -> bool {
+    word.chars().any(|c| "aeiouy".contains(c))
+}
+
+/// A small English suffix stripper, so `blogs`, `blogging` and `blogged` all become `blog`.
+pub fn stem(word: &str) -> String {
+    let word = word.to_lowercase();
+    if word.len() <= 3 || !word.is_ascii() {
+        return word;
+    }
+    let mut stem = word.as_str();
+    if let Some(rest) = stem.strip_suffix("sses") {
+        return format!("{rest}ss");
+    }
+    if let Some(rest) = stem.strip_suffix("ies") {
+        return format!("{rest}y");
+    }
+    if stem.ends_with('s')
+        && !stem.ends_with("ss")
+        && !stem.ends_with("us")
+        && !stem.ends_with("is")
+    {
+        stem = &stem[..stem.len() - 1];
+    }
+    let mut stripped = false;
+    for suffix in ["ing", "ed"] {
+        if let Some(rest) = stem.strip_suffix(suffix)
+            && rest.len() >= 3
+            && has_vowel(rest)
+        {
+            stem = rest;
+            stripped = true;
+            break;
+        }
+    }
+    let mut stem = stem.to_string();
+    for (suffix, replacement) in [("ation", "ate"), ("ness", ""), ("ment", ""), ("ly", "")] {
+        if let Some(rest) = stem.strip_suffix(suffix)
+            && rest.len() >= 4
+        {
+            stem = format!("{rest}{replacement}");
+            break;
+        }
+    }
+    let bytes = stem.as_bytes();
+    if stripped
+        && bytes.len() >= 2
+        && bytes[bytes.len() - 1] == bytes[bytes.len() - 2]
+        && !b"aeiouylsz".contains(&bytes[bytes.len() - 1])
+    {
+        stem.pop();
+    }
+    if stem.len() > 4 && stem.ends_with('e') {
+        stem.pop();
+    }
+    stem
+}
+
+/// Optimal string alignment distance, i.e. Levenshtein distance that also counts swapping two
+/// neighbouring characters as one edit.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+fn allowed_typos(term: &str) -> usize {
+    match term.chars().count() {
+        0..4 => 0,
+        4..8 => 1,
+        _ => 2,
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// HTML-escapes `text[start..end]`, wrapping the tokens whose stems are in `terms` in `<mark>`.
+fn highlight(
+    text: &str,
+    tokens: &[Token],
+    terms: &HashSet<String>,
+    start: usize,
+    end: usize,
+) -> String {
+    let mut html = String::new();
+    let mut at = start;
+    for token in tokens
+        .iter()
+        .filter(|token| token.start >= start && token.end <= end)
+    {
+        if terms.contains(&stem(token.text)) {
+            html.push_str(&escape(&text[at..token.start]));
+            html.push_str("<mark>");
+            html.push_str(&escape(token.text));
+            html.push_str("</mark>");
+            at = token.end;
+        }
+    }
+    html.push_str(&escape(&text[at..end]));
+    html
+}
+
+fn snippet(text: &str, terms: &HashSet<String>) -> String {
+    let tokens = tokenize(text);
+    let Some(first) = tokens
+        .iter()
+        .position(|token| terms.contains(&stem(token.text)))
+        .or((!tokens.is_empty()).then_some(0))
+    else {
+        return escape(text);
+    };
+    let from = first.saturating_sub(SNIPPET_BEFORE);
+    let to = (first + SNIPPET_AFTER).min(tokens.len() - 1);
+    let start = if from == 0 { 0 } else { tokens[from].start };
+    let end = if to == tokens.len() - 1 {
+        text.len()
+    } else {
+        tokens[to].end
+    };
+    let mut html = highlight(text, &tokens, terms, start, end);
+    if start > 0 {
+        html.insert_str(0, "… ");
+    }
+    if end < text.len() {
+        html.push_str(" …");
+    }
+    html
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub project: Project,
+    pub score: f32,
+    /// The title as HTML, with matches in `<mark>`.
+    pub title: String,
+    /// Part of the description around the first match as HTML, with matches in `<mark>`.
+    pub snippet: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchIndex {
+    projects: Vec<Project>,
+    /// Weighted length of each project.
+    lengths: Vec<f32>,
+    average_length: f32,
+    /// Stemmed term to the projects containing it and its weighted frequency there.
+    postings: HashMap<String, Vec<(usize, f32)>>,
+}
+
+impl SearchIndex {
+    pub fn new(projects: impl IntoIterator<Item = Project>) -> Self {
+        let mut index = SearchIndex::default();
+        for (doc, project) in projects.into_iter().enumerate() {
+            let mut frequencies: HashMap<String, f32> = HashMap::new();
+            let mut length = 0.0;
+            let mut add = |text: &str, weight: f32| {
+                for token in tokenize(text) {
+                    *frequencies.entry(stem(token.text)).or_default() += weight;
+                    length += weight;
+                }
+            };
+            add(&project.title, TITLE_WEIGHT);
+            add(&project.description, DESCRIPTION_WEIGHT);
+            for tag in &project.tags {
+                add(tag, TAG_WEIGHT);
+            }
+            for link in &project.links {
+                add(&link.name, LINK_WEIGHT);
+            }
+            for (term, frequency) in frequencies {
+                index
+                    .postings
+                    .entry(term)
+                    .or_default()
+                    .push((doc, frequency));
+            }
+            index.lengths.push(length);
+            index.projects.push(project);
+        }
+        index.average_length =
+            index.lengths.iter().sum::<f32>() / index.lengths.len().max(1) as f32;
+        index
+    }
+
+    /// Indexed terms matching query term `term`, with how much a match counts.
+    fn expand(&self, term: &str) -> Vec<(&str, f32)> {
+        let typos = allowed_typos(term);
+        self.postings
+            .keys()
+            .filter_map(|candidate| {
+                if candidate == term {
+                    return Some((candidate.as_str(), 1.0));
+                }
+                if typos == 0 || candidate.len().abs_diff(term.len()) > typos {
+                    return None;
+                }
+                let edits = distance(candidate, term);
+                (edits <= typos).then(|| (candidate.as_str(), 1.0 / (1.0 + edits as f32)))
+            })
+            .collect()
+    }
+
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let total = self.projects.len() as f32;
+        let mut scores: HashMap<usize, f32> = HashMap::new();
+        let mut matched = HashSet::new();
+        let terms: HashSet<String> = tokenize(query)
+            .iter()
+            .map(|token| stem(token.text))
+            .collect();
+        for term in &terms {
+            let mut best: HashMap<usize, f32> = HashMap::new();
+            for (candidate, weight) in self.expand(term) {
+                matched.insert(candidate.to_string());
+                let postings = &self.postings[candidate];
+                let df = postings.len() as f32;
+                let idf = (1.0 + (total - df + 0.5) / (df + 0.5)).ln();
+                for &(doc, frequency) in postings {
+                    let norm = 1.0 - B + B * self.lengths[doc] / self.average_length;
+                    let score = weight * idf * frequency * (K1 + 1.0) / (frequency + K1 * norm);
+                    let entry = best.entry(doc).or_default();
+                    *entry = entry.max(score);
+                }
+            }
+            for (doc, score) in best {
+                *scores.entry(doc).or_default() += score;
+            }
+        }
+
+        let mut ranked: Vec<(usize, f32)> = scores.into_iter().collect();
+        ranked.sort_by(|(a_doc, a), (b_doc, b)| b.total_cmp(a).then(a_doc.cmp(b_doc)));
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(doc, score)| {
+                let project = &self.projects[doc];
+                let title_tokens = tokenize(&project.title);
+                SearchHit {
+                    title: highlight(
+                        &project.title,
+                        &title_tokens,
+                        &matched,
+                        0,
+                        project.title.len(),
+                    ),
+                    snippet: snippet(&project.description, &matched),
+                    project: project.clone(),
+                    score,
+                }
+            })
+            .collect()
+    }
+
+    pub fn global() -> Arc<SearchIndex> {
+        INDEX.read().unwrap().clone()
+    }
+    pub fn install(self) {
+        *INDEX.write().unwrap() = Arc::new(self);
+    }
+
+    /// Rebuilds the global index from the live projects in the database.
+    pub async fn rebuild(db: &mut MySqlConnection) -> Result<()> {
+        let projects = Projects::get(db).await?;
+        SearchIndex::new(projects.projects).install();
+        Ok(())
+    }
+}
+
+/// Rebuilds the global index after a write, logging instead of failing if it cannot.
+pub async fn reindex(db: &mut MySqlConnection) {
+    if let Err(e) = SearchIndex::rebuild(db).await {
+        log::error!("cannot rebuild the search index: {e}");
+    }
+}
+
+/// Builds the search index once the database is available.
+pub struct Indexer;
+
+#[rocket::async_trait]
+impl Fairing for Indexer {
+    fn info(&self) -> Info {
+        Info {
+            name: "Search Index",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let Some(pool) = BloggerDatabase::fetch(rocket) else {
+            return;
+        };
+        match pool.acquire().await {
+            Ok(mut db) => reindex(&mut db).await,
+            Err(e) => log::error!("cannot build the search index: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builders::ProjectBuilder;
+
+    fn index() -> SearchIndex {
+        SearchIndex::new(
+            [
+                (
+                    1,
+                    "Blog engine",
+                    "A portfolio backend serving projects over a JSON API.",
+                    "Rust",
+                ),
+                (
+                    2,
+                    "Weather station",
+                    "Collects readings and blogs about them using a tiny Rust blogging engine.",
+                    "Embedded",
+                ),
+                (3, "Chess engine", "Plays chess <badly>.", "C++"),
+            ]
+            .map(|(id, title, description, tag)| {
+                ProjectBuilder::sample(&[tag])
+                    .id(id)
+                    .title(title)
+                    .description(description)
+                    .bulid()
+                    .unwrap()
+            }),
+        )
+    }
+
+    #[test]
+    fn stemming() {
+        assert_eq!(stem("blogging"), "blog");
+        assert_eq!(stem("blogged"), "blog");
+        assert_eq!(stem("Blogs"), "blog");
+        assert_eq!(stem("libraries"), "library");
+        assert_eq!(stem("creating"), stem("create"));
+        assert_eq!(stem("animation"), stem("animated"));
+        assert_eq!(stem("rust"), "rust");
+        assert_eq!(stem("status"), "status");
+    }
+    #[test]
+    fn tokens_and_distance() {
+        let tokens = tokenize("Hi, wörld-2!");
+        assert_eq!(
+            tokens.iter().map(|token| token.text).collect::<Vec<_>>(),
+            vec!["Hi", "wörld", "2"]
+        );
+        assert_eq!(distance("rust", "rsut"), 1);
+        assert_eq!(distance("kitten", "sitting"), 3);
+    }
+    #[test]
+    fn ranking() {
+        let hits = index().search("blogging", 10);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].project.id, Some(1), "title matches rank first");
+        assert_eq!(hits[0].title, "<mark>Blog</mark> engine");
+        assert_eq!(hits[1].project.id, Some(2));
+        assert!(hits[1].snippet.contains("<mark>blogs</mark>"));
+        assert!(hits[1].snippet.contains("<mark>blogging</mark>"));
+        assert!(index().search("", 10).is_empty());
+        assert_eq!(index().search("rust", 1).len(), 1);
+    }
+    #[test]
+    fn typos_and_snippets() {
+        let hits = index().search("portfolo", 10);
+        assert_eq!(hits[0].project.id, Some(1));
+        assert!(hits[0].snippet.contains("<mark>portfolio</mark>"));
+
+        let hits = index().search("chess", 10);
+        assert_eq!(hits[0].snippet, "Plays <mark>chess</mark> &lt;badly&gt;.");
+        assert_eq!(index().search("cehss", 10)[0].project.id, Some(3));
+        assert!(index().search("chs", 10).is_empty());
+    }
+}
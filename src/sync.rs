@@ -0,0 +1,406 @@
+//! Two-way synchronisation between the database and a content directory.
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use rocket_db_pools::sqlx::{Connection, MySqlConnection};
+use serde::Serialize;
+
+use crate::{
+    Project, ProjectBuilderError, Projects, SyncError, builders::Edit, content, diff::ProjectDiff,
+};
+
+/// Author recorded on revisions written by a sync.
+pub const SYNC_AUTHOR: &str = "sync";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    ToDatabase,
+    ToDirectory,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum SyncAction {
+    /// Create `project` on the target side. `path` is the content file it came from, if any.
+    Add {
+        project: Project,
+        path: Option<PathBuf>,
+    },
+    /// Overwrite project `id` on the target side with `project`.
+    Update {
+        id: u32,
+        diff: Box<ProjectDiff>,
+        project: Project,
+        path: Option<PathBuf>,
+    },
+    Delete {
+        id: Option<u32>,
+        title: String,
+        path: Option<PathBuf>,
+    },
+    /// Leave the content file at `path` alone: project `id` is in the trash and has to be restored
+    /// before it can be synced.
+    Skip { id: u32, path: PathBuf },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncPlan {
+    pub direction: Direction,
+    pub actions: Vec<SyncAction>,
+}
+
+impl SyncPlan {
+    /// Computes what has to change on the target side of `direction` for it to match the source.
+    /// Projects are matched by id; directory files without an id have never been synced. Files of
+    /// projects in `trashed` are skipped when pushing.
+    pub fn new(
+        database: &Projects,
+        trashed: &HashSet<u32>,
+        directory: &[(PathBuf, Project)],
+        direction: Direction,
+    ) -> Result<SyncPlan, ProjectBuilderError> {
+        let in_database: HashMap<u32, &Project> = database
+            .projects
+            .iter()
+            .filter_map(|project| Some((project.id?, project)))
+            .collect();
+        let in_directory: HashMap<u32, (&PathBuf, &Project)> = directory
+            .iter()
+            .filter_map(|(path, project)| Some((project.id?, (path, project))))
+            .collect();
+        let mut actions = vec![];
+
+        match direction {
+            Direction::ToDatabase => {
+                for (path, source) in directory {
+                    if let Some(id) = source.id
+                        && trashed.contains(&id)
+                    {
+                        actions.push(SyncAction::Skip {
+                            id,
+                            path: path.clone(),
+                        });
+                        continue;
+                    }
+                    match source
+                        .id
+                        .and_then(|id| in_database.get(&id).map(|t| (id, t)))
+                    {
+                        Some((id, target)) => {
+                            let diff = target.diff(source);
+                            if !diff.is_empty() {
+                                actions.push(SyncAction::Update {
+                                    id,
+                                    project: diff.apply_to((*target).clone())?,
+                                    diff: Box::new(diff),
+                                    path: Some(path.clone()),
+                                });
+                            }
+                        }
+                        None => actions.push(SyncAction::Add {
+                            project: source.clone(),
+                            path: Some(path.clone()),
+                        }),
+                    }
+                }
+                for target in &database.projects {
+                    if let Some(id) = target.id
+                        && !in_directory.contains_key(&id)
+                    {
+                        actions.push(SyncAction::Delete {
+                            id: Some(id),
+                            title: target.title.clone(),
+                            path: None,
+                        });
+                    }
+                }
+            }
+            Direction::ToDirectory => {
+                let mut kept = HashSet::new();
+                for source in &database.projects {
+                    let Some(id) = source.id else { continue };
+                    match in_directory.get(&id) {
+                        Some((path, target)) => {
+                            kept.insert(*path);
+                            let diff = target.diff(source);
+                            if !diff.is_empty() {
+                                actions.push(SyncAction::Update {
+                                    id,
+                                    project: diff.apply_to((*target).clone())?,
+                                    diff: Box::new(diff),
+                                    path: Some((*path).clone()),
+                                });
+                            }
+                        }
+                        None => actions.push(SyncAction::Add {
+                            project: source.clone(),
+                            path: None,
+                        }),
+                    }
+                }
+                for (path, target) in directory {
+                    if !kept.contains(path) {
+                        actions.push(SyncAction::Delete {
+                            id: target.id,
+                            title: target.title.clone(),
+                            path: Some(path.clone()),
+                        });
+                    }
+                }
+            }
+        }
+        actions.sort_by_key(|action| match action {
+            SyncAction::Add { .. } => 0,
+            SyncAction::Update { .. } => 1,
+            SyncAction::Delete { .. } => 2,
+            SyncAction::Skip { .. } => 3,
+        });
+        Ok(SyncPlan { direction, actions })
+    }
+
+    /// Whether nothing would change. Skipped files change nothing.
+    pub fn is_empty(&self) -> bool {
+        self.actions
+            .iter()
+            .all(|action| matches!(action, SyncAction::Skip { .. }))
+    }
+
+    /// Applies the plan. Database changes happen in a single transaction; directory changes are
+    /// written file by file.
+    pub async fn apply(&self, db: &mut MySqlConnection, dir: &Path) -> Result<(), SyncError> {
+        match self.direction {
+            Direction::ToDatabase => {
+                let mut tx = db.begin().await?;
+                let mut written_back = vec![];
+                for action in &self.actions {
+                    match action {
+                        SyncAction::Add { project, path } => {
+                            let id = project.insert(SYNC_AUTHOR, &mut tx).await?;
+                            if let Some(path) = path
+                                && project.id.is_none()
+                            {
+                                let mut builder = project.clone().edit();
+                                builder.id(id);
+                                written_back.push((path, builder.bulid()?));
+                            }
+                        }
+                        SyncAction::Update { id, project, .. } => {
+                            project.update(*id, SYNC_AUTHOR, &mut tx).await?;
+                        }
+                        SyncAction::Delete { id: Some(id), .. } => {
+                            Project::delete(*id, &mut tx).await?;
+                        }
+                        SyncAction::Delete { id: None, .. } | SyncAction::Skip { .. } => {}
+                    }
+                }
+                tx.commit().await?;
+                for (path, project) in written_back {
+                    fs::write(path, content::render(path, &project)?)?;
+                }
+            }
+            Direction::ToDirectory => {
+                for action in &self.actions {
+                    match action {
+                        SyncAction::Add { project, .. } => {
+                            let path = dir.join(file_name(project));
+                            fs::write(&path, content::render(&path, project)?)?;
+                        }
+                        SyncAction::Update {
+                            project,
+                            path: Some(path),
+                            ..
+                        } => fs::write(path, content::render(path, project)?)?,
+                        SyncAction::Delete {
+                            path: Some(path), ..
+                        } => fs::remove_file(path)?,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn file_name(project: &Project) -> String {
+    let slug = project
+        .title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    match project.id {
+        Some(id) => format!("{id}-{slug}.md"),
+        None => format!("{slug}.md"),
+    }
+}
+
+impl fmt::Display for SyncPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for action in &self.actions {
+            match action {
+                SyncAction::Add { project, .. } => writeln!(f, "+ add {:?}", project.title)?,
+                SyncAction::Update {
+                    id, diff, project, ..
+                } => {
+                    writeln!(f, "~ update #{id} {:?}", project.title)?;
+                    for line in diff.to_string().lines() {
+                        writeln!(f, "    {line}")?;
+                    }
+                }
+                SyncAction::Delete { id, title, path } => match (id, path) {
+                    (_, Some(path)) => writeln!(f, "- delete {:?} ({})", title, path.display())?,
+                    (Some(id), None) => writeln!(f, "- delete #{id} {title:?}")?,
+                    (None, None) => writeln!(f, "- delete {title:?}")?,
+                },
+                SyncAction::Skip { id, path } => writeln!(
+                    f,
+                    "! skip #{id} ({}): in the trash, restore it first",
+                    path.display()
+                )?,
+            }
+        }
+        if self.is_empty() {
+            writeln!(f, "Nothing to do.")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builders::ProjectBuilder;
+
+    #[test]
+    fn plan_to_database() {
+        let database = Projects {
+            projects: vec![
+                ProjectBuilder::sample(&["Rust"])
+                    .id(1)
+                    .title("same")
+                    .bulid()
+                    .unwrap(),
+                ProjectBuilder::sample(&["Rust"])
+                    .id(2)
+                    .title("old")
+                    .bulid()
+                    .unwrap(),
+                ProjectBuilder::sample(&["Rust"])
+                    .id(3)
+                    .title("gone")
+                    .bulid()
+                    .unwrap(),
+            ],
+        };
+        let directory = vec![
+            (
+                PathBuf::from("1.md"),
+                ProjectBuilder::sample(&["Rust"])
+                    .id(1)
+                    .title("same")
+                    .bulid()
+                    .unwrap(),
+            ),
+            (
+                PathBuf::from("2.md"),
+                ProjectBuilder::sample(&["Rust"])
+                    .id(2)
+                    .title("new")
+                    .bulid()
+                    .unwrap(),
+            ),
+            (
+                PathBuf::from("new.md"),
+                ProjectBuilder::sample(&["Go"])
+                    .title("fresh")
+                    .bulid()
+                    .unwrap(),
+            ),
+            (
+                PathBuf::from("5.md"),
+                ProjectBuilder::sample(&["Rust"])
+                    .id(5)
+                    .title("trashed")
+                    .bulid()
+                    .unwrap(),
+            ),
+        ];
+        let trashed = HashSet::from([5]);
+        let plan = SyncPlan::new(&database, &trashed, &directory, Direction::ToDatabase).unwrap();
+        assert_eq!(plan.actions.len(), 4);
+        assert!(
+            matches!(&plan.actions[0], SyncAction::Add { project, .. } if project.title == "fresh")
+        );
+        assert!(matches!(
+            &plan.actions[1],
+            SyncAction::Update { id: 2, project, .. } if project.title == "new" && project.id == Some(2)
+        ));
+        assert!(matches!(
+            &plan.actions[2],
+            SyncAction::Delete { id: Some(3), .. }
+        ));
+        assert!(matches!(
+            &plan.actions[3],
+            SyncAction::Skip { id: 5, path } if path == Path::new("5.md")
+        ));
+    }
+    #[test]
+    fn plan_to_directory() {
+        let database = Projects {
+            projects: vec![
+                ProjectBuilder::sample(&["Rust"])
+                    .id(1)
+                    .title("same")
+                    .bulid()
+                    .unwrap(),
+                ProjectBuilder::sample(&["Go"])
+                    .id(4)
+                    .title("Brand New!")
+                    .bulid()
+                    .unwrap(),
+            ],
+        };
+        let directory = vec![
+            (
+                PathBuf::from("1.md"),
+                ProjectBuilder::sample(&["Java"])
+                    .id(1)
+                    .title("same")
+                    .bulid()
+                    .unwrap(),
+            ),
+            (
+                PathBuf::from("draft.md"),
+                ProjectBuilder::sample(&["Go"])
+                    .title("draft")
+                    .bulid()
+                    .unwrap(),
+            ),
+        ];
+        let plan = SyncPlan::new(
+            &database,
+            &HashSet::new(),
+            &directory,
+            Direction::ToDirectory,
+        )
+        .unwrap();
+        assert_eq!(plan.actions.len(), 3);
+        assert!(
+            matches!(&plan.actions[0], SyncAction::Add { project, .. } if file_name(project) == "4-brand-new.md")
+        );
+        assert!(matches!(
+            &plan.actions[1],
+            SyncAction::Update { id: 1, project, .. } if project.tags == vec!["Rust"]
+        ));
+        assert!(matches!(
+            &plan.actions[2],
+            SyncAction::Delete { path: Some(path), .. } if path == Path::new("draft.md")
+        ));
+    }
+}
@@ -0,0 +1,737 @@
+//! Canonical tags, their aliases and how they are displayed.
+//!
+//! The registry lives in the database and is cached in memory so [`ProjectBuilder::add_tag`]
+//! can resolve aliases without a connection.
+//!
+//! [`ProjectBuilder::add_tag`]: crate::builders::ProjectBuilder::add_tag
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use itertools::Itertools;
+use rocket::{
+    Orbit, Rocket,
+    fairing::{Fairing, Info, Kind},
+};
+use rocket_db_pools::{
+    Database,
+    sqlx::{self, Connection, MySqlConnection, Result, Row, mysql::MySqlRow},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Project, TagError, db::BloggerDatabase, revisions::Revision, validation::ValidationPolicy,
+};
+
+static REGISTRY: LazyLock<RwLock<Arc<TagRegistry>>> = LazyLock::new(RwLock::default);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tag {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Canonical name of the parent tag.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+}
+
+/// A tag along with the tags under it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TagNode {
+    #[serde(flatten)]
+    pub tag: Tag,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TagNode>,
+}
+
+/// Key tags are matched by, so `Rust`, `rust` and ` RUST ` are the same tag.
+pub fn normalize(tag: &str) -> String {
+    tag.split_whitespace().join(" ").to_lowercase()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TagRegistry {
+    tags: BTreeMap<String, Tag>,
+    /// Normalized name or alias to canonical name.
+    lookup: HashMap<String, String>,
+    /// Canonical name to the canonical names of its children.
+    children: HashMap<String, Vec<String>>,
+}
+
+impl TagRegistry {
+    pub fn new(tags: impl IntoIterator<Item = Tag>) -> Self {
+        let mut registry = TagRegistry::default();
+        for tag in tags {
+            for alias in &tag.aliases {
+                registry.lookup.insert(normalize(alias), tag.name.clone());
+            }
+            registry
+                .lookup
+                .insert(normalize(&tag.name), tag.name.clone());
+            registry.tags.insert(tag.name.clone(), tag);
+        }
+        for tag in registry.tags.values() {
+            if let Some(parent) = &tag.parent
+                && registry.tags.contains_key(parent)
+            {
+                registry
+                    .children
+                    .entry(parent.clone())
+                    .or_default()
+                    .push(tag.name.clone());
+            }
+        }
+        registry
+    }
+
+    /// The registry used by [`ProjectBuilder::add_tag`](crate::builders::ProjectBuilder::add_tag).
+    pub fn global() -> Arc<TagRegistry> {
+        REGISTRY.read().unwrap().clone()
+    }
+    /// Replaces the global registry.
+    pub fn install(self) {
+        *REGISTRY.write().unwrap() = Arc::new(self);
+    }
+
+    pub fn tags(&self) -> impl Iterator<Item = &Tag> {
+        self.tags.values()
+    }
+    /// Looks up a tag by its canonical name or any alias.
+    pub fn get(&self, tag: &str) -> Option<&Tag> {
+        self.lookup
+            .get(&normalize(tag))
+            .and_then(|name| self.tags.get(name))
+    }
+    /// The registry as a forest, with tags whose parent is unknown at the top.
+    pub fn tree(&self) -> Vec<TagNode> {
+        self.tags
+            .values()
+            .filter(|tag| {
+                tag.parent
+                    .as_ref()
+                    .is_none_or(|parent| !self.tags.contains_key(parent))
+            })
+            .map(|tag| self.subtree(tag))
+            .collect()
+    }
+    /// Looks up `tag` by its canonical name or any alias, along with the tags under it.
+    pub fn node(&self, tag: &str) -> Option<TagNode> {
+        self.get(tag).map(|tag| self.subtree(tag))
+    }
+    fn subtree(&self, tag: &Tag) -> TagNode {
+        TagNode {
+            tag: tag.clone(),
+            children: self
+                .children
+                .get(&tag.name)
+                .into_iter()
+                .flatten()
+                .filter_map(|child| self.tags.get(child))
+                .map(|child| self.subtree(child))
+                .collect(),
+        }
+    }
+    /// Normalized names of `tag` and every tag under it, with their aliases, since stored rows may
+    /// still carry those.
+    pub fn descendants(&self, tag: &str) -> HashSet<String> {
+        let canonical = self.resolve(tag);
+        let mut found = HashSet::from([normalize(tag), normalize(&canonical)]);
+        let mut pending = vec![canonical];
+        while let Some(name) = pending.pop() {
+            if let Some(tag) = self.tags.get(&name) {
+                found.extend(tag.aliases.iter().map(|alias| normalize(alias)));
+            }
+            for child in self.children.get(&name).into_iter().flatten() {
+                if found.insert(normalize(child)) {
+                    pending.push(child.clone());
+                }
+            }
+        }
+        found
+    }
+    /// Whether `project` has `tag` or any tag under it.
+    pub fn matches(&self, project: &Project, tag: &str) -> bool {
+        let wanted = self.descendants(tag);
+        project
+            .tags
+            .iter()
+            .any(|tag| wanted.contains(&normalize(tag)))
+    }
+
+    /// The canonical name of `tag`, or `tag` with its whitespace tidied up if it is not
+    /// registered.
+    pub fn resolve(&self, tag: &str) -> String {
+        match self.get(tag) {
+            Some(tag) => tag.name.clone(),
+            None => tag.split_whitespace().join(" "),
+        }
+    }
+
+    pub async fn load(db: &mut MySqlConnection) -> Result<TagRegistry> {
+        let mut aliases = sqlx::query("SELECT tag_id, alias FROM tag_aliases ORDER BY alias")
+            .map(|row: MySqlRow| (row.get::<u32, _>("tag_id"), row.get::<String, _>("alias")))
+            .fetch_all(&mut *db)
+            .await?
+            .into_iter()
+            .into_group_map();
+        let tags = sqlx::query(
+            "SELECT tag.id, tag.name, tag.color, tag.icon, tag.description, parent.name AS parent \
+             FROM tags tag LEFT JOIN tags parent ON parent.id = tag.parent_id ORDER BY tag.name",
+        )
+        .map(|row: MySqlRow| Tag {
+            name: row.get("name"),
+            color: row.get("color"),
+            icon: row.get("icon"),
+            description: row.get("description"),
+            aliases: aliases.remove(&row.get("id")).unwrap_or_default(),
+            parent: row.get("parent"),
+        })
+        .fetch_all(&mut *db)
+        .await?;
+        Ok(TagRegistry::new(tags))
+    }
+
+    /// Reloads the global registry from the database.
+    pub async fn reload(db: &mut MySqlConnection) -> Result<()> {
+        TagRegistry::load(db).await?.install();
+        Ok(())
+    }
+}
+
+impl Tag {
+    /// Checks the name and aliases against the tag length rules of `policy`.
+    pub fn validate_with(&self, policy: &ValidationPolicy) -> Result<(), TagError> {
+        match std::iter::once(&self.name)
+            .chain(&self.aliases)
+            .find(|name| !policy.tag_ok(name))
+        {
+            Some(name) => Err(TagError::Name(name.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Creates or updates the registry entry for this tag, replacing its aliases, and reloads the
+    /// global registry.
+    pub async fn save(&self, db: &mut MySqlConnection) -> Result<(), TagError> {
+        self.validate_with(ValidationPolicy::global())?;
+        let mut tx = db.begin().await?;
+        let parent_id = match &self.parent {
+            Some(parent) => {
+                let registry = TagRegistry::load(&mut tx).await?;
+                if registry
+                    .descendants(&self.name)
+                    .contains(&normalize(parent))
+                {
+                    return Err(TagError::Cycle(parent.clone()));
+                }
+                let id = TagOperation::tag_id(parent, &mut tx).await?;
+                Some(id.ok_or_else(|| TagError::NotFound(parent.clone()))?)
+            }
+            None => None,
+        };
+        sqlx::query(
+            "INSERT INTO tags (name, color, icon, description, parent_id) VALUES (?, ?, ?, ?, ?) \
+             ON DUPLICATE KEY UPDATE name = VALUES(name), color = VALUES(color), \
+             icon = VALUES(icon), description = VALUES(description), parent_id = VALUES(parent_id)",
+        )
+        .bind(&self.name)
+        .bind(&self.color)
+        .bind(&self.icon)
+        .bind(&self.description)
+        .bind(parent_id)
+        .execute(&mut *tx)
+        .await?;
+        let id = TagOperation::tag_id(&self.name, &mut tx)
+            .await?
+            .expect("the tag was just saved");
+        sqlx::query("DELETE FROM tag_aliases WHERE tag_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        for alias in &self.aliases {
+            sqlx::query(
+                "INSERT INTO tag_aliases (alias, tag_id) VALUES (?, ?) \
+                 ON DUPLICATE KEY UPDATE tag_id = VALUES(tag_id)",
+            )
+            .bind(alias)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        TagRegistry::reload(db).await?;
+        Ok(())
+    }
+}
+
+/// An admin change to a tag, applied to every project that uses it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum TagOperation {
+    /// Renames `from` to `to`, keeping its registry entry. If only `to` is registered, `from` is
+    /// merged into it instead.
+    Rename { from: String, to: String },
+    /// Replaces every tag in `from` with `into`, keeping the old names as aliases of `into`.
+    Merge { from: Vec<String>, into: String },
+    /// Removes `tag` from every project and from the registry.
+    Delete { tag: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagReport {
+    #[serde(flatten)]
+    pub operation: TagOperation,
+    pub affected: usize,
+    pub projects: Vec<u32>,
+    pub dry_run: bool,
+}
+
+impl TagOperation {
+    /// The normalized names the operation replaces: the tags it names and, for those that are
+    /// registered under that name, their aliases, since stored rows may still carry those.
+    fn sources(&self, registry: &TagRegistry) -> Vec<String> {
+        let named = match self {
+            TagOperation::Rename { from, .. } => std::slice::from_ref(from),
+            TagOperation::Merge { from, .. } => from.as_slice(),
+            TagOperation::Delete { tag } => std::slice::from_ref(tag),
+        };
+        let mut sources = vec![];
+        for tag in named {
+            sources.push(normalize(tag));
+            if let Some(registered) = registry.get(tag)
+                && normalize(&registered.name) == normalize(tag)
+            {
+                sources.extend(registered.aliases.iter().map(|alias| normalize(alias)));
+            }
+        }
+        sources
+    }
+    fn replacement(&self) -> Option<&str> {
+        match self {
+            TagOperation::Rename { to, .. } => Some(to),
+            TagOperation::Merge { into, .. } => Some(into),
+            TagOperation::Delete { .. } => None,
+        }
+    }
+
+    /// The tags a project ends up with, or `None` if the operation does not touch them. Aliases
+    /// of the affected tags are looked up in `registry`.
+    pub fn retag(&self, tags: &[String], registry: &TagRegistry) -> Option<Vec<String>> {
+        let sources = self.sources(registry);
+        if !tags.iter().any(|tag| sources.contains(&normalize(tag))) {
+            return None;
+        }
+        let mut retagged: Vec<String> = vec![];
+        for tag in tags {
+            let tag = if sources.contains(&normalize(tag)) {
+                match self.replacement() {
+                    Some(replacement) => replacement.to_string(),
+                    None => continue,
+                }
+            } else {
+                tag.clone()
+            };
+            if !retagged
+                .iter()
+                .any(|other| normalize(other) == normalize(&tag))
+            {
+                retagged.push(tag);
+            }
+        }
+        Some(retagged)
+    }
+
+    async fn tag_id(name: &str, db: &mut MySqlConnection) -> Result<Option<u32>> {
+        sqlx::query("SELECT id FROM tags WHERE name = ?")
+            .bind(name)
+            .map(|row: MySqlRow| row.get("id"))
+            .fetch_optional(db)
+            .await
+    }
+
+    /// Applies the operation to the registry. Returns whether any source tag was registered.
+    async fn update_registry(&self, db: &mut MySqlConnection) -> Result<bool, TagError> {
+        match self {
+            TagOperation::Rename { from, to } => {
+                match (Self::tag_id(from, db).await?, Self::tag_id(to, db).await?) {
+                    (Some(from_id), Some(to_id)) if from_id != to_id => {
+                        Err(TagError::Exists(to.clone()))
+                    }
+                    // Nothing to rename, but `to` can take `from` in as an alias.
+                    (None, Some(_)) => {
+                        Self::merge_registry(std::slice::from_ref(from), to, db).await
+                    }
+                    (None, None) => Ok(false),
+                    (Some(from_id), _) => {
+                        sqlx::query("UPDATE tags SET name = ? WHERE id = ?")
+                            .bind(to)
+                            .bind(from_id)
+                            .execute(&mut *db)
+                            .await?;
+                        Ok(true)
+                    }
+                }
+            }
+            TagOperation::Merge { from, into } => Self::merge_registry(from, into, db).await,
+            TagOperation::Delete { tag } => {
+                let result = sqlx::query("DELETE FROM tags WHERE name = ?")
+                    .bind(tag)
+                    .execute(&mut *db)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+        }
+    }
+
+    /// Deletes the registered tags in `from`, moving their aliases and children to `into` and
+    /// keeping their names as aliases of it. Returns whether any of them was registered.
+    async fn merge_registry(
+        from: &[String],
+        into: &str,
+        db: &mut MySqlConnection,
+    ) -> Result<bool, TagError> {
+        let into_id = match Self::tag_id(into, db).await? {
+            Some(id) => id,
+            None => sqlx::query("INSERT INTO tags (name) VALUES (?)")
+                .bind(into)
+                .execute(&mut *db)
+                .await?
+                .last_insert_id() as u32,
+        };
+        let mut registered = false;
+        for tag in from {
+            if let Some(id) = Self::tag_id(tag, db).await?
+                && id != into_id
+            {
+                sqlx::query("UPDATE tag_aliases SET tag_id = ? WHERE tag_id = ?")
+                    .bind(into_id)
+                    .bind(id)
+                    .execute(&mut *db)
+                    .await?;
+                sqlx::query("UPDATE tags SET parent_id = ? WHERE parent_id = ?")
+                    .bind(into_id)
+                    .bind(id)
+                    .execute(&mut *db)
+                    .await?;
+                sqlx::query("DELETE FROM tags WHERE id = ?")
+                    .bind(id)
+                    .execute(&mut *db)
+                    .await?;
+                registered = true;
+            }
+            if normalize(tag) != normalize(into) {
+                sqlx::query(
+                    "INSERT INTO tag_aliases (alias, tag_id) VALUES (?, ?) \
+                     ON DUPLICATE KEY UPDATE tag_id = VALUES(tag_id)",
+                )
+                .bind(tag)
+                .bind(into_id)
+                .execute(&mut *db)
+                .await?;
+            }
+        }
+        Ok(registered)
+    }
+
+    /// Applies the operation to every project and the registry in one transaction, recording a
+    /// revision for each live project it changes. With `dry_run` the transaction is rolled back,
+    /// so the report shows what would happen.
+    pub async fn apply(
+        &self,
+        author: &str,
+        dry_run: bool,
+        db: &mut MySqlConnection,
+    ) -> Result<TagReport, TagError> {
+        if let Some(name) = self.replacement()
+            && !ValidationPolicy::global().tag_ok(name)
+        {
+            return Err(TagError::Name(name.to_string()));
+        }
+        let mut tx = db.begin().await?;
+        let registry = TagRegistry::load(&mut tx).await?;
+        let tags = sqlx::query("SELECT project_id, tag FROM project_tags ORDER BY id FOR UPDATE")
+            .map(|row: MySqlRow| (row.get::<u32, _>("project_id"), row.get::<String, _>("tag")))
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .into_group_map();
+
+        let mut projects = vec![];
+        let mut untagged = vec![];
+        for (id, tags) in tags.into_iter().sorted_by_key(|(id, _)| *id) {
+            let Some(retagged) = self.retag(&tags, &registry) else {
+                continue;
+            };
+            if retagged.is_empty() {
+                untagged.push(id);
+                continue;
+            }
+            sqlx::query("DELETE FROM project_tags WHERE project_id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            for tag in retagged {
+                sqlx::query("INSERT INTO project_tags (project_id, tag) VALUES (?, ?)")
+                    .bind(id)
+                    .bind(tag)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            projects.push(id);
+        }
+        if !untagged.is_empty() {
+            return Err(TagError::LastTag(untagged));
+        }
+
+        let registered = self.update_registry(&mut tx).await?;
+        if projects.is_empty() && !registered {
+            let tag = match self {
+                TagOperation::Rename { from, .. } => from.clone(),
+                TagOperation::Merge { from, .. } => from.join(", "),
+                TagOperation::Delete { tag } => tag.clone(),
+            };
+            return Err(TagError::NotFound(tag));
+        }
+        for id in &projects {
+            if let Some(project) = Project::get(*id, &mut tx).await? {
+                Revision::record(*id, &project, author, &mut tx).await?;
+            }
+        }
+
+        if !dry_run {
+            tx.commit().await?;
+            TagRegistry::reload(db).await?;
+        }
+        Ok(TagReport {
+            operation: self.clone(),
+            affected: projects.len(),
+            projects,
+            dry_run,
+        })
+    }
+}
+
+impl fmt::Display for TagOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TagOperation::Rename { from, to } => write!(f, "rename {from:?} to {to:?}"),
+            TagOperation::Merge { from, into } => write!(f, "merge {from:?} into {into:?}"),
+            TagOperation::Delete { tag } => write!(f, "delete {tag:?}"),
+        }
+    }
+}
+
+/// Loads the tag registry into memory once the database is available.
+pub struct TagLoader;
+
+#[rocket::async_trait]
+impl Fairing for TagLoader {
+    fn info(&self) -> Info {
+        Info {
+            name: "Tag Registry",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let Some(pool) = BloggerDatabase::fetch(rocket) else {
+            return;
+        };
+        let loaded = match pool.acquire().await {
+            Ok(mut db) => TagRegistry::reload(&mut db).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = loaded {
+            log::error!("cannot load the tag registry: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> TagRegistry {
+        TagRegistry::new([
+            Tag {
+                name: "Rust".to_string(),
+                color: Some("#dea584".to_string()),
+                icon: None,
+                description: None,
+                aliases: vec!["rust-lang".to_string(), "rustlang".to_string()],
+                parent: Some("Languages".to_string()),
+            },
+            Tag {
+                name: "Web Development".to_string(),
+                color: None,
+                icon: None,
+                description: Some("Sites and web apps".to_string()),
+                aliases: vec!["webdev".to_string()],
+                parent: None,
+            },
+        ])
+    }
+
+    #[test]
+    fn resolve_aliases() {
+        let registry = registry();
+        assert_eq!(registry.resolve("rust"), "Rust");
+        assert_eq!(registry.resolve("Rust-Lang"), "Rust");
+        assert_eq!(registry.resolve(" web   development "), "Web Development");
+        assert_eq!(registry.resolve("WEBDEV"), "Web Development");
+        assert_eq!(registry.resolve("  MySQL "), "MySQL");
+        assert_eq!(
+            registry
+                .descendants("Rust")
+                .into_iter()
+                .sorted()
+                .collect_vec(),
+            vec!["rust", "rust-lang", "rustlang"]
+        );
+        assert_eq!(
+            registry
+                .get("rustlang")
+                .and_then(|tag| tag.color.as_deref()),
+            Some("#dea584")
+        );
+        assert_eq!(
+            registry.tags().map(|tag| tag.name.as_str()).collect_vec(),
+            vec!["Rust", "Web Development"]
+        );
+    }
+    #[test]
+    fn tag_names() {
+        let policy = ValidationPolicy {
+            tag_max: 4,
+            ..Default::default()
+        };
+        let mut tag = registry().get("rust").unwrap().clone();
+        tag.aliases.clear();
+        assert!(tag.validate_with(&policy).is_ok());
+        tag.aliases.push("rustlang".to_string());
+        assert!(
+            matches!(tag.validate_with(&policy), Err(TagError::Name(name)) if name == "rustlang")
+        );
+        tag.name = String::new();
+        assert!(matches!(tag.validate_with(&policy), Err(TagError::Name(name)) if name.is_empty()));
+    }
+    #[test]
+    fn hierarchy() {
+        let tag = |name: &str, parent: Option<&str>| Tag {
+            name: name.to_string(),
+            color: None,
+            icon: None,
+            description: None,
+            aliases: vec![],
+            parent: parent.map(str::to_string),
+        };
+        let registry = TagRegistry::new([
+            tag("Languages", None),
+            tag("Rust", Some("Languages")),
+            tag("Async Rust", Some("Rust")),
+            tag("Frameworks", None),
+            tag("Vue", Some("Frameworks")),
+        ]);
+        let tree = registry.tree();
+        assert_eq!(
+            tree.iter().map(|node| node.tag.name.as_str()).collect_vec(),
+            vec!["Frameworks", "Languages"]
+        );
+        assert_eq!(tree[1].children[0].tag.name, "Rust");
+        assert_eq!(tree[1].children[0].children[0].tag.name, "Async Rust");
+
+        let languages = registry.descendants("languages");
+        assert!(languages.contains("async rust"));
+        assert!(!languages.contains("vue"));
+
+        let project = crate::builders::ProjectBuilder::new()
+            .title("blogger")
+            .description("portfolio backend")
+            .add_tag("Async Rust")
+            .add_link(crate::builders::LinkBuilder::sample())
+            .bulid()
+            .unwrap();
+        assert!(registry.matches(&project, "Languages"));
+        assert!(registry.matches(&project, "async rust"));
+        assert!(!registry.matches(&project, "Frameworks"));
+    }
+    #[test]
+    fn retag_projects() {
+        let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect_vec();
+        let empty = TagRegistry::default();
+        let rename = TagOperation::Rename {
+            from: "Rsut".to_string(),
+            to: "Rust".to_string(),
+        };
+        assert_eq!(
+            rename.retag(&tags(&["rsut", "Web"]), &empty),
+            Some(tags(&["Rust", "Web"]))
+        );
+        assert_eq!(rename.retag(&tags(&["Web"]), &empty), None);
+
+        let merge = TagOperation::Merge {
+            from: vec!["rust-lang".to_string(), "rustlang".to_string()],
+            into: "Rust".to_string(),
+        };
+        assert_eq!(
+            merge.retag(&tags(&["rustlang", "Web", "Rust", "rust-lang"]), &empty),
+            Some(tags(&["Rust", "Web"]))
+        );
+
+        let delete = TagOperation::Delete {
+            tag: "Web".to_string(),
+        };
+        assert_eq!(
+            delete.retag(&tags(&["Rust", "web"]), &empty),
+            Some(tags(&["Rust"]))
+        );
+        assert_eq!(delete.retag(&tags(&["Web"]), &empty), Some(vec![]));
+
+        // Rows may still carry an alias of the renamed tag, but not the other way round.
+        let registry = registry();
+        let rename = TagOperation::Rename {
+            from: "Rust".to_string(),
+            to: "Rust Lang".to_string(),
+        };
+        assert_eq!(
+            rename.retag(&tags(&["rustlang", "Web"]), &registry),
+            Some(tags(&["Rust Lang", "Web"]))
+        );
+        let rename = TagOperation::Rename {
+            from: "rustlang".to_string(),
+            to: "Rust Lang".to_string(),
+        };
+        assert_eq!(rename.retag(&tags(&["Rust"]), &registry), None);
+    }
+    #[test]
+    fn operation_json() {
+        let operation: TagOperation =
+            serde_json::from_str(r#"{ "op": "merge", "from": ["webdev"], "into": "Web" }"#)
+                .unwrap();
+        assert_eq!(
+            operation,
+            TagOperation::Merge {
+                from: vec!["webdev".to_string()],
+                into: "Web".to_string(),
+            }
+        );
+        let report = TagReport {
+            operation,
+            affected: 2,
+            projects: vec![1, 3],
+            dry_run: true,
+        };
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["op"], "merge");
+        assert_eq!(json["affected"], 2);
+    }
+}
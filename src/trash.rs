@@ -0,0 +1,198 @@
+//! Deleted projects, kept around until they are restored or purged.
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use rocket::{
+    Orbit, Rocket,
+    fairing::{Fairing, Info, Kind},
+    figment::Figment,
+    tokio,
+};
+use rocket_db_pools::{
+    Database,
+    sqlx::{self, MySqlConnection, Result, Row, mysql::MySqlRow},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Project,
+    db::{BloggerDatabase, load},
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrashedProject {
+    #[serde(flatten)]
+    pub project: Project,
+    /// Unix timestamp in seconds.
+    pub deleted_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Trash {
+    pub projects: Vec<TrashedProject>,
+}
+
+impl Trash {
+    pub async fn get(db: &mut MySqlConnection) -> Result<Trash> {
+        let mut deleted_at: HashMap<u32, i64> = sqlx::query(
+            "SELECT id, CAST(UNIX_TIMESTAMP(deleted_at) AS SIGNED) AS deleted_at \
+             FROM projects WHERE deleted_at IS NOT NULL",
+        )
+        .map(|row: MySqlRow| (row.get("id"), row.get("deleted_at")))
+        .fetch_all(&mut *db)
+        .await?
+        .into_iter()
+        .collect();
+        let projects = load(db, None, true)
+            .await?
+            .into_iter()
+            .filter_map(|project| {
+                Some(TrashedProject {
+                    deleted_at: deleted_at.remove(&project.id?)?,
+                    project,
+                })
+            })
+            .collect();
+        Ok(Trash { projects })
+    }
+
+    /// The ids of every project in the trash.
+    pub async fn ids(db: &mut MySqlConnection) -> Result<HashSet<u32>> {
+        sqlx::query("SELECT id FROM projects WHERE deleted_at IS NOT NULL")
+            .map(|row: MySqlRow| row.get("id"))
+            .fetch_all(db)
+            .await
+            .map(HashSet::from_iter)
+    }
+
+    /// Permanently deletes projects that have been in the trash for longer than `retention`.
+    pub async fn purge_expired(retention: Duration, db: &mut MySqlConnection) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM projects WHERE deleted_at < CURRENT_TIMESTAMP - INTERVAL ? SECOND",
+        )
+        .bind(retention.as_secs())
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+impl Project {
+    pub async fn in_trash(id: u32, db: &mut MySqlConnection) -> Result<bool> {
+        Ok(
+            sqlx::query("SELECT id FROM projects WHERE id = ? AND deleted_at IS NOT NULL")
+                .bind(id)
+                .fetch_optional(db)
+                .await?
+                .is_some(),
+        )
+    }
+
+    /// Takes project `id` out of the trash. Returns `false` if it is not in the trash.
+    pub async fn restore(id: u32, db: &mut MySqlConnection) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE projects SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL",
+        )
+        .bind(id)
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Permanently deletes project `id` from the trash, along with its tags and links.
+    pub async fn purge(id: u32, db: &mut MySqlConnection) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM projects WHERE id = ? AND deleted_at IS NOT NULL")
+            .bind(id)
+            .execute(db)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TrashConfig {
+    #[serde(default = "TrashConfig::default_retention_days")]
+    pub retention_days: u64,
+}
+impl TrashConfig {
+    fn default_retention_days() -> u64 {
+        30
+    }
+    pub fn retention(&self) -> Duration {
+        Duration::from_secs(self.retention_days * 24 * 60 * 60)
+    }
+
+    /// Reads `[trash]`, falling back to the defaults if the section is missing.
+    pub fn from_figment(figment: &Figment) -> Result<Self, Box<rocket::figment::Error>> {
+        if figment.contains("trash") {
+            figment.extract_inner("trash").map_err(Box::new)
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+impl Default for TrashConfig {
+    fn default() -> Self {
+        TrashConfig {
+            retention_days: Self::default_retention_days(),
+        }
+    }
+}
+
+/// Purges expired projects from the trash every hour, using `[trash]` from `Rocket.toml`.
+pub struct TrashPurger;
+
+#[rocket::async_trait]
+impl Fairing for TrashPurger {
+    fn info(&self) -> Info {
+        Info {
+            name: "Trash Purger",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let Some(pool) = BloggerDatabase::fetch(rocket).map(|db| (**db).clone()) else {
+            return;
+        };
+        let retention = match TrashConfig::from_figment(rocket.figment()) {
+            Ok(config) => config.retention(),
+            Err(e) => return log::error!("invalid [trash] config, not purging: {e}"),
+        };
+        let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+        tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+                let purged = match pool.acquire().await {
+                    Ok(mut db) => Trash::purge_expired(retention, &mut db).await,
+                    Err(e) => Err(e),
+                };
+                match purged {
+                    Ok(0) => {}
+                    Ok(purged) => log::info!("purged {purged} project(s) from the trash"),
+                    Err(e) => log::error!("cannot purge the trash: {e}"),
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::figment::providers::{Format, Toml};
+
+    use super::*;
+
+    #[test]
+    fn retention_from_config() {
+        let config = |toml: &str| TrashConfig::from_figment(&Figment::from(Toml::string(toml)));
+        assert_eq!(
+            config("[trash]\nretention_days = 2").unwrap().retention(),
+            Duration::from_secs(2 * 24 * 60 * 60)
+        );
+        assert_eq!(config("").unwrap().retention_days, 30);
+        assert!(config("[trash]\nretention_days = \"forever\"").is_err());
+    }
+}
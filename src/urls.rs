@@ -0,0 +1,149 @@
+//! Canonical forms of link and cover URLs, configured under `[validation.urls]`.
+//!
+//! Canonicalizing lowercases the host, drops the scheme's default port and strips tracking
+//! parameters like `utm_source` or `fbclid`, leaving the other parameters exactly as written.
+//! Optionally, `http` URLs are upgraded to `https`.
+use serde::Deserialize;
+use url::Url;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct UrlPolicy {
+    /// Leave URLs exactly as given.
+    pub keep: bool,
+    pub force_https: bool,
+    /// Query parameters to remove, compared case-insensitively. A trailing `*` matches any
+    /// parameter starting with the rest.
+    pub strip_params: Vec<String>,
+}
+
+impl Default for UrlPolicy {
+    fn default() -> Self {
+        UrlPolicy {
+            keep: false,
+            force_https: false,
+            strip_params: [
+                "utm_*", "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid",
+                "mc_cid", "mc_eid", "igshid", "_hsenc", "_hsmi", "mkt_tok", "ixid", "ixlib",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
+
+/// Decodes the name of a raw `name=value` query parameter.
+fn param_name(param: &str) -> String {
+    let name = param.split_once('=').map_or(param, |(name, _)| name);
+    url::form_urlencoded::parse(name.as_bytes())
+        .next()
+        .map(|(name, _)| name.into_owned())
+        .unwrap_or_default()
+}
+
+impl UrlPolicy {
+    fn strips(&self, name: &str) -> bool {
+        self.strip_params
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => name
+                    .get(..prefix.len())
+                    .is_some_and(|start| start.eq_ignore_ascii_case(prefix)),
+                None => name.eq_ignore_ascii_case(pattern),
+            })
+    }
+
+    pub fn canonicalize(&self, url: &Url) -> Url {
+        let mut url = url.clone();
+        if self.keep {
+            return url;
+        }
+        if self.force_https && url.scheme() == "http" {
+            _ = url.set_scheme("https");
+        }
+        if let Some(host) = url.host_str()
+            && host.chars().any(|c| c.is_ascii_uppercase())
+        {
+            let host = host.to_ascii_lowercase();
+            _ = url.set_host(Some(&host));
+        }
+        if url.port().is_some() && url.port() == default_port(url.scheme()) {
+            _ = url.set_port(None);
+        }
+        if let Some(query) = url.query() {
+            let kept = query
+                .split('&')
+                .filter(|param| !param.is_empty() && !self.strips(&param_name(param)))
+                .collect::<Vec<_>>();
+            let kept = (!kept.is_empty()).then(|| kept.join("&"));
+            if kept.as_deref() != Some(query) {
+                url.set_query(kept.as_deref());
+            }
+        }
+        url
+    }
+}
+
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        "ftp" => Some(21),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(policy: &UrlPolicy, url: &str) -> String {
+        policy.canonicalize(&Url::parse(url).unwrap()).to_string()
+    }
+
+    #[test]
+    fn strips_trackers() {
+        let policy = UrlPolicy::default();
+        assert_eq!(
+            canonical(
+                &policy,
+                "https://Example.COM:443/a?utm_source=x&id=1&UTM_Medium=y&q=a%20b+c&fbclid=z#top"
+            ),
+            "https://example.com/a?id=1&q=a%20b+c#top"
+        );
+        assert_eq!(
+            canonical(&policy, "https://example.com/?utm_campaign=x"),
+            "https://example.com/"
+        );
+        assert_eq!(
+            canonical(
+                &policy,
+                "https://images.unsplash.com/photo-1?ixlib=rb-4.0.3&ixid=M3w%3D&w=1587&fit=crop"
+            ),
+            "https://images.unsplash.com/photo-1?w=1587&fit=crop"
+        );
+    }
+    #[test]
+    fn configurable() {
+        let policy = UrlPolicy {
+            force_https: true,
+            strip_params: vec!["ix*".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            canonical(
+                &policy,
+                "http://images.example.com:443/p?q=80&ixlib=rb-4.0.3&ixid=M3w%3D&utm_source=x"
+            ),
+            "https://images.example.com/p?q=80&utm_source=x"
+        );
+        let keep = UrlPolicy {
+            keep: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            canonical(&keep, "https://example.com/?utm_source=x"),
+            "https://example.com/?utm_source=x"
+        );
+    }
+}
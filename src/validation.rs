@@ -0,0 +1,369 @@
+//! Content rules applied by the builders, configured under `[validation]` in `Rocket.toml`.
+use std::sync::{LazyLock, OnceLock};
+
+use rocket::{
+    Build, Rocket,
+    fairing::{self, Fairing, Info, Kind},
+    figment::Figment,
+};
+use serde::Deserialize;
+use url::Url;
+
+use crate::urls::UrlPolicy;
+
+static POLICY: OnceLock<ValidationPolicy> = OnceLock::new();
+static PERMISSIVE: LazyLock<ValidationPolicy> = LazyLock::new(|| ValidationPolicy {
+    title_min: 0,
+    title_max: usize::MAX,
+    description_min: 0,
+    description_max: None,
+    tag_min: 0,
+    tag_max: usize::MAX,
+    link_name_min: 0,
+    link_name_max: usize::MAX,
+    tag_count_min: 0,
+    tag_count_max: None,
+    link_count_min: 0,
+    link_count_max: None,
+    allowed_tags: None,
+    link_schemes: vec![],
+    link_hosts: None,
+    require_cover: false,
+    unique_links: false,
+    unique_link_names: false,
+    urls: UrlPolicy {
+        keep: true,
+        ..Default::default()
+    },
+});
+
+/// Lengths are counted in characters. The defaults follow the column sizes in `schema.sql`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ValidationPolicy {
+    pub title_min: usize,
+    pub title_max: usize,
+    pub description_min: usize,
+    pub description_max: Option<usize>,
+    pub tag_min: usize,
+    pub tag_max: usize,
+    pub link_name_min: usize,
+    pub link_name_max: usize,
+    /// How many tags a project needs, and may have.
+    pub tag_count_min: usize,
+    pub tag_count_max: Option<usize>,
+    /// How many links a project needs, and may have.
+    pub link_count_min: usize,
+    pub link_count_max: Option<usize>,
+    /// Only these tags may be used, if set.
+    pub allowed_tags: Option<Vec<String>>,
+    /// Allowed link schemes. Empty allows any scheme.
+    pub link_schemes: Vec<String>,
+    /// Only links to these hosts or their subdomains are allowed, if set.
+    pub link_hosts: Option<Vec<String>>,
+    pub require_cover: bool,
+    /// Links must have distinct URLs, once canonicalized.
+    pub unique_links: bool,
+    /// Links must have distinct names, including the ones suggested for unnamed links.
+    pub unique_link_names: bool,
+    /// How link and cover URLs are canonicalized.
+    pub urls: UrlPolicy,
+}
+
+impl Default for ValidationPolicy {
+    fn default() -> Self {
+        ValidationPolicy {
+            title_min: 1,
+            title_max: 255,
+            description_min: 1,
+            description_max: None,
+            tag_min: 1,
+            tag_max: 100,
+            link_name_min: 1,
+            link_name_max: 100,
+            tag_count_min: 1,
+            tag_count_max: None,
+            link_count_min: 1,
+            link_count_max: None,
+            allowed_tags: None,
+            link_schemes: vec!["http".to_string(), "https".to_string()],
+            link_hosts: None,
+            require_cover: false,
+            unique_links: true,
+            unique_link_names: true,
+            urls: UrlPolicy::default(),
+        }
+    }
+}
+
+fn within(len: usize, min: usize, max: Option<usize>) -> bool {
+    len >= min && max.is_none_or(|max| len <= max)
+}
+
+impl ValidationPolicy {
+    /// The policy used by [`ProjectBuilder::bulid`](crate::builders::ProjectBuilder::bulid) and
+    /// [`LinkBuilder::bulid`](crate::builders::LinkBuilder::bulid). Defaults until one is
+    /// installed.
+    pub fn global() -> &'static ValidationPolicy {
+        POLICY.get_or_init(ValidationPolicy::default)
+    }
+    /// Makes `self` the global policy. Returns `false` if one was already in use.
+    pub fn install(self) -> bool {
+        POLICY.set(self).is_ok()
+    }
+    /// Accepts anything the database can hold and leaves URLs as they are. Stored projects are
+    /// loaded with it, so tightening the policy never hides or trims projects saved under an older
+    /// one; the current policy applies again when they are next written.
+    pub fn permissive() -> &'static ValidationPolicy {
+        &PERMISSIVE
+    }
+
+    /// Reads `[validation]`, falling back to the defaults if the section is missing.
+    pub fn from_figment(figment: &Figment) -> Result<Self, Box<rocket::figment::Error>> {
+        if figment.contains("validation") {
+            figment.extract_inner("validation").map_err(Box::new)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn title_ok(&self, title: &str) -> bool {
+        within(title.chars().count(), self.title_min, Some(self.title_max))
+    }
+    pub fn description_ok(&self, description: &str) -> bool {
+        within(
+            description.chars().count(),
+            self.description_min,
+            self.description_max,
+        )
+    }
+    pub fn tag_ok(&self, tag: &str) -> bool {
+        within(tag.chars().count(), self.tag_min, Some(self.tag_max))
+    }
+    pub fn tag_allowed(&self, tag: &str) -> bool {
+        self.allowed_tags
+            .as_ref()
+            .is_none_or(|allowed| allowed.iter().any(|allowed| allowed == tag))
+    }
+    pub fn link_name_ok(&self, name: &str) -> bool {
+        within(
+            name.chars().count(),
+            self.link_name_min,
+            Some(self.link_name_max),
+        )
+    }
+    pub fn scheme_allowed(&self, url: &Url) -> bool {
+        self.link_schemes.is_empty()
+            || self
+                .link_schemes
+                .iter()
+                .any(|scheme| scheme.eq_ignore_ascii_case(url.scheme()))
+    }
+    pub fn canonicalize(&self, url: &Url) -> Url {
+        self.urls.canonicalize(url)
+    }
+    pub fn host_allowed(&self, url: &Url) -> bool {
+        let Some(hosts) = &self.link_hosts else {
+            return true;
+        };
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        hosts.iter().any(|allowed| {
+            host.eq_ignore_ascii_case(allowed)
+                || host
+                    .to_ascii_lowercase()
+                    .ends_with(&format!(".{}", allowed.to_ascii_lowercase()))
+        })
+    }
+}
+
+/// Installs the policy from `[validation]` in `Rocket.toml`, refusing to launch if it is invalid.
+pub struct Validation;
+
+#[rocket::async_trait]
+impl Fairing for Validation {
+    fn info(&self) -> Info {
+        Info {
+            name: "Validation Policy",
+            kind: Kind::Ignite,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        match ValidationPolicy::from_figment(rocket.figment()) {
+            Ok(policy) => {
+                if !policy.install() {
+                    log::warn!("validation policy was already in use, ignoring [validation]");
+                }
+                Ok(rocket)
+            }
+            Err(e) => {
+                log::error!("invalid [validation] config: {e}");
+                Err(rocket)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::figment::providers::{Format, Toml};
+
+    use super::*;
+    use crate::{
+        LinkBuilderError, ProjectBuilderError,
+        builders::{LinkBuilder, ProjectBuilder},
+    };
+
+    #[test]
+    fn policy_from_config() {
+        let figment = Figment::from(Toml::string(
+            "[validation]\ntitle_max = 10\nallowed_tags = [\"Rust\"]\nrequire_cover = true\n\
+             tag_count_max = 3\nlink_count_max = 2",
+        ));
+        let policy = ValidationPolicy::from_figment(&figment).unwrap();
+        assert_eq!(policy.title_max, 10);
+        assert_eq!(policy.tag_count_max, Some(3));
+        assert_eq!(policy.link_count_max, Some(2));
+        assert_eq!(policy.tag_max, 100);
+        assert!(policy.require_cover);
+        assert!(!policy.urls.force_https);
+
+        let figment = Figment::from(Toml::string(
+            "[validation.urls]\nforce_https = true\nstrip_params = [\"ref\"]",
+        ));
+        let policy = ValidationPolicy::from_figment(&figment).unwrap();
+        assert!(policy.urls.force_https);
+        assert_eq!(policy.urls.strip_params, ["ref"]);
+        assert_eq!(
+            ValidationPolicy::from_figment(&Figment::new()).unwrap(),
+            ValidationPolicy::default()
+        );
+    }
+    #[test]
+    fn project_rules() {
+        let policy = ValidationPolicy {
+            title_max: 5,
+            tag_count_max: Some(1),
+            allowed_tags: Some(vec!["Rust".to_string()]),
+            require_cover: true,
+            ..Default::default()
+        };
+        let mut builder = ProjectBuilder::new();
+        builder
+            .title("too long")
+            .description("")
+            .add_tag("Rust")
+            .add_tag("Go")
+            .add_link(LinkBuilder::sample());
+        assert_eq!(
+            builder.validate_with(&policy),
+            vec![
+                ProjectBuilderError::TitleLength,
+                ProjectBuilderError::DescriptionLength,
+                ProjectBuilderError::Cover,
+                ProjectBuilderError::TooManyTags,
+                ProjectBuilderError::UnknownTag,
+            ]
+        );
+        assert_eq!(
+            builder.bulid_with(&policy),
+            Err(ProjectBuilderError::TitleLength)
+        );
+        builder.title("ok").description("fine").remove_tag("Go");
+        builder.cover(Url::parse("https://example.com/cover.png").unwrap());
+        assert!(builder.bulid_with(&policy).is_ok());
+    }
+    #[test]
+    fn permissive() {
+        let strict = ValidationPolicy {
+            title_max: 3,
+            link_count_max: Some(1),
+            link_hosts: Some(vec!["github.com".to_string()]),
+            ..Default::default()
+        };
+        let link = |url: &str| {
+            LinkBuilder::new()
+                .name("Link")
+                .url(Url::parse(url).unwrap())
+                .bulid_with(ValidationPolicy::permissive())
+                .unwrap()
+        };
+        let mut builder = ProjectBuilder::new();
+        builder
+            .title("stored long ago")
+            .description("d")
+            .add_tag("Rust")
+            .add_link(link("https://example.com/a"))
+            .add_link(link("ftp://example.com/b"));
+        assert!(builder.bulid_with(&strict).is_err());
+        let project = builder.bulid_with(ValidationPolicy::permissive()).unwrap();
+        assert_eq!(project.links.len(), 2);
+
+        // Stored before tracking parameters were stripped, so only the current policy sees the
+        // links as duplicates.
+        let mut builder = ProjectBuilder::sample(&["Rust"]);
+        builder.add_link(link("https://example.com/?utm_source=x"));
+        assert_eq!(builder.bulid(), Err(ProjectBuilderError::DuplicateLinks));
+        let project = builder.bulid_with(ValidationPolicy::permissive()).unwrap();
+        assert_eq!(
+            project.links[1].link.as_str(),
+            "https://example.com/?utm_source=x"
+        );
+
+        // Saved while the policy asked for no tags or links.
+        let mut builder = ProjectBuilder::new();
+        builder.title("bare").description("d");
+        assert_eq!(
+            builder.validate_with(&ValidationPolicy::default()),
+            [ProjectBuilderError::Tags, ProjectBuilderError::Links]
+        );
+        assert!(builder.bulid_with(ValidationPolicy::permissive()).is_ok());
+    }
+    #[test]
+    fn suggested_link_names() {
+        let unnamed = |url: &str| {
+            LinkBuilder::new()
+                .url(Url::parse(url).unwrap())
+                .bulid()
+                .unwrap()
+        };
+        let mut builder = ProjectBuilder::sample(&["Rust"]);
+        builder
+            .add_link(unnamed("https://github.com/a/app"))
+            .add_link(unnamed("https://github.com/a/app-docs"));
+        assert_eq!(
+            builder.bulid(),
+            Err(ProjectBuilderError::DuplicateLinkNames)
+        );
+        assert!(builder.bulid_with(ValidationPolicy::permissive()).is_ok());
+    }
+    #[test]
+    fn link_rules() {
+        let policy = ValidationPolicy {
+            link_name_max: 4,
+            link_hosts: Some(vec!["github.com".to_string()]),
+            ..Default::default()
+        };
+        let link = |name: &str, url: &str| {
+            LinkBuilder::new()
+                .name(name)
+                .url(Url::parse(url).unwrap())
+                .bulid_with(&policy)
+        };
+        assert!(link("Code", "https://gist.github.com/x").is_ok());
+        assert_eq!(
+            link("Source", "https://github.com"),
+            Err(LinkBuilderError::NameLength)
+        );
+        assert_eq!(
+            link("Code", "ftp://github.com"),
+            Err(LinkBuilderError::Scheme)
+        );
+        assert_eq!(
+            link("Code", "https://notgithub.com"),
+            Err(LinkBuilderError::Host)
+        );
+    }
+}